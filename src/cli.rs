@@ -0,0 +1,282 @@
+use crate::ccgt::GridTradeBot;
+use crate::error::{Error, Result};
+use crate::max::{OrderRequest, OrderState, OrderType};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/* default candle period in minutes */
+const CANDLE_PERIOD: u32 = 60;
+
+/*
+ * operator actions, run instead of the trading loop:
+ *   ccgt place --market <id> --side <buy|sell> --volume <n> [--type <ord_type>]
+ *              [--price <n>] [--stop-price <n>]
+ *   ccgt cancel [--market <id>] [--side <buy|sell>] [--group-id <id>]
+ *   ccgt orders --market <id> [--state <state,...>] [--group-id <id>]
+ *               [--since <rfc3339>] [--until <rfc3339>]
+ *   ccgt trades --market <id> [--since <rfc3339>] [--until <rfc3339>]
+ *   ccgt depth --market <id> [--limit <n>]
+ *   ccgt candles --market <id> [--period <minutes>] [--limit <n>] [--since <rfc3339>]
+ *   ccgt market-trades --market <id> [--limit <n>]
+ */
+pub async fn run(command: &str, args: &[String]) -> Result<()> {
+    let options = parse_options(args)?;
+
+    match command {
+        "place" => place_order(&options).await,
+        "cancel" => cancel_orders(&options).await,
+        "orders" => list_orders(&options).await,
+        "trades" => list_trades(&options).await,
+        "depth" => show_depth(&options).await,
+        "candles" => list_candles(&options).await,
+        "market-trades" => list_market_trades(&options).await,
+        _ => Err(Error::Config(format!("unknown command: {}", command))),
+    }
+}
+
+/* read "--key value" pairs */
+fn parse_options(args: &[String]) -> Result<HashMap<String, String>> {
+    let mut options = HashMap::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let key = match arg.strip_prefix("--") {
+            Some(key) => key,
+            None => return Err(Error::Config(format!("unexpected argument: {}", arg))),
+        };
+
+        match args.next() {
+            Some(value) => options.insert(key.to_string(), value.clone()),
+            None => return Err(Error::Config(format!("{} needs a value", arg))),
+        };
+    }
+
+    Ok(options)
+}
+
+fn required<'a>(options: &'a HashMap<String, String>, key: &str) -> Result<&'a str> {
+    match options.get(key) {
+        Some(value) => Ok(value),
+        None => Err(Error::Config(format!("--{} is required", key))),
+    }
+}
+
+fn parse_number<T: FromStr>(options: &HashMap<String, String>, key: &str) -> Result<Option<T>> {
+    match options.get(key) {
+        Some(value) => match value.parse() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(Error::Config(format!(
+                "invalid number for --{}: {}",
+                key, value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_time(options: &HashMap<String, String>, key: &str) -> Result<Option<DateTime<Utc>>> {
+    match options.get(key) {
+        Some(value) => match DateTime::parse_from_rfc3339(value) {
+            Ok(time) => Ok(Some(time.with_timezone(&Utc))),
+            Err(_) => Err(Error::Config(format!(
+                "invalid time for --{}: {}",
+                key, value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+/* place a single order of any type, limit by default */
+async fn place_order(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let side = required(options, "side")?.parse().map_err(Error::Config)?;
+    let ord_type = match options.get("type") {
+        Some(ord_type) => ord_type.parse().map_err(Error::Config)?,
+        None => OrderType::Limit,
+    };
+    let volume = match parse_number(options, "volume")? {
+        Some(volume) => volume,
+        None => return Err(Error::Config("--volume is required".into())),
+    };
+
+    let mut order = OrderRequest::new(ord_type, side, volume);
+    if let Some(price) = parse_number(options, "price")? {
+        order = order.price(price);
+    }
+    if let Some(stop_price) = parse_number(options, "stop-price")? {
+        order = order.stop_price(stop_price);
+    }
+
+    let mut trade_bot = GridTradeBot::new()?;
+    trade_bot.sync_catalog().await?;
+    let id = trade_bot.submit_order(market, order).await?;
+    println!(
+        "[{}] placed {} order {}: {} {}",
+        market,
+        ord_type.as_str(),
+        id,
+        side,
+        volume
+    );
+
+    Ok(())
+}
+
+async fn cancel_orders(options: &HashMap<String, String>) -> Result<()> {
+    let market = options.get("market").map(String::as_str);
+    let side = match options.get("side") {
+        Some(side) => Some(side.parse().map_err(Error::Config)?),
+        None => None,
+    };
+    let group_id = parse_number(options, "group-id")?;
+
+    let trade_bot = GridTradeBot::new()?;
+    for order in trade_bot.cancel_orders(market, side, group_id).await? {
+        println!(
+            "[{}] cancelled order {}: {} {} @ {}",
+            &order.market,
+            order.id,
+            order.side,
+            &order.remaining_volume,
+            order.price.unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+/* order history as csv */
+async fn list_orders(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let states = match options.get("state") {
+        Some(states) => states
+            .split(',')
+            .map(|state| state.parse().map_err(Error::Config))
+            .collect::<Result<Vec<OrderState>>>()?,
+        None => OrderState::ALL.to_vec(),
+    };
+    let group_id = parse_number(options, "group-id")?;
+    let since = parse_time(options, "since")?;
+    let until = parse_time(options, "until")?;
+
+    let trade_bot = GridTradeBot::new()?;
+
+    println!("id,client_oid,group_id,created_at,market,side,state,price,volume,executed_volume");
+    let mut orders = trade_bot.order_history(market, &states, group_id, since, until);
+    while let Some(order) = orders.next().await {
+        let order = order?;
+        println!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            order.id,
+            order.client_oid.unwrap_or_default(),
+            order.group_id.map(|id| id.to_string()).unwrap_or_default(),
+            order.created_at.to_rfc3339(),
+            order.market,
+            order.side,
+            order.state.as_str(),
+            order.price.unwrap_or_default(),
+            order.volume,
+            order.executed_volume
+        );
+    }
+
+    Ok(())
+}
+
+/* fills as csv, ready to be loaded into the fills database */
+async fn list_trades(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let since = parse_time(options, "since")?;
+    let until = parse_time(options, "until")?;
+
+    let trade_bot = GridTradeBot::new()?;
+
+    println!("id,created_at,market,side,price,volume,funds,fee,fee_currency,order_id");
+    let mut trades = trade_bot.trade_history(market, since, until);
+    while let Some(trade) = trades.next().await {
+        let trade = trade?;
+        println!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            trade.id,
+            trade.created_at.to_rfc3339(),
+            trade.market,
+            trade.side.map(|side| side.as_str()).unwrap_or_default(),
+            trade.price,
+            trade.volume,
+            trade.funds,
+            trade.fee.unwrap_or_default(),
+            trade.fee_currency.unwrap_or_default(),
+            trade.order_id.map(|id| id.to_string()).unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+/* order book as csv, asks from the best price outwards followed by bids */
+async fn show_depth(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let limit = parse_number(options, "limit")?;
+
+    let trade_bot = GridTradeBot::new()?;
+    let depth = trade_bot.get_depth(market, limit).await?;
+
+    println!("side,price,volume");
+    for level in &depth.asks {
+        println!("ask,{},{}", level.price, level.volume);
+    }
+    for level in &depth.bids {
+        println!("bid,{},{}", level.price, level.volume);
+    }
+
+    Ok(())
+}
+
+/* candles as csv */
+async fn list_candles(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let period = parse_number(options, "period")?.unwrap_or(CANDLE_PERIOD);
+    let limit = parse_number(options, "limit")?;
+    let since = parse_time(options, "since")?;
+
+    let trade_bot = GridTradeBot::new()?;
+
+    println!("time,open,high,low,close,volume");
+    for candle in trade_bot.get_candles(market, period, limit, since).await? {
+        println!(
+            "{},{},{},{},{},{}",
+            candle.time.to_rfc3339(),
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume
+        );
+    }
+
+    Ok(())
+}
+
+/* public trades as csv, side is the taker side */
+async fn list_market_trades(options: &HashMap<String, String>) -> Result<()> {
+    let market = required(options, "market")?;
+    let limit = parse_number(options, "limit")?;
+
+    let trade_bot = GridTradeBot::new()?;
+
+    println!("id,created_at,side,price,volume");
+    for trade in trade_bot.get_market_trades(market, limit).await? {
+        println!(
+            "{},{},{},{},{}",
+            trade.id,
+            trade.created_at.to_rfc3339(),
+            trade.side.map(|side| side.as_str()).unwrap_or_default(),
+            trade.price,
+            trade.volume
+        );
+    }
+
+    Ok(())
+}
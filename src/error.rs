@@ -0,0 +1,80 @@
+use crate::max::OrderError;
+use reqwest::StatusCode;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /* the request never got a response (dns, tls, timeout, connection reset) */
+    Transport(reqwest::Error),
+    /* a non-success status without a MAX error body, e.g. 502 from the gateway */
+    Http { status: StatusCode, body: String },
+    /* MAX rejected the request with its own error code */
+    Api { code: i64, message: String },
+    /* the response does not match the expected model */
+    Decode(serde_json::Error),
+    /* config.yaml or the environment is invalid */
+    Config(String),
+    /* the order is refused locally before it reaches MAX */
+    InvalidOrder(String),
+    /* the request is shed by the client side rate limiter */
+    Throttled(String),
+    /* an order batch failed as a whole, the message is shared by its orders */
+    BatchFailed(String),
+    /* a strategy stopped trading at runtime, e.g. on a risk limit */
+    Halted(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Transport(err) => write!(f, "transport error: {}", err),
+            Error::Http { status, body } => write!(f, "http error {}: {}", status, body),
+            Error::Api { code, message } => write!(f, "MAX error {}: {}", code, message),
+            Error::Decode(err) => write!(f, "decode error: {}", err),
+            Error::Config(message) => write!(f, "config error: {}", message),
+            Error::InvalidOrder(message) => write!(f, "invalid order: {}", message),
+            Error::Throttled(path) => write!(f, "{} is throttled by the rate limiter", path),
+            Error::BatchFailed(message) => write!(f, "order batch failed: {}", message),
+            Error::Halted(message) => write!(f, "trading halted: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /* failures that may succeed when the same request is sent again */
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Transport(err) => err.is_timeout() || err.is_connect() || err.is_request(),
+            Error::Http { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Transport(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Decode(err)
+    }
+}
+
+/* MAX omits the code for some batch rejections, those are reported as code 0 */
+impl From<OrderError> for Error {
+    fn from(err: OrderError) -> Error {
+        match err {
+            OrderError::Detail { code, message } => Error::Api { code, message },
+            OrderError::Message(message) => Error::Api { code: 0, message },
+        }
+    }
+}
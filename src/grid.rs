@@ -0,0 +1,981 @@
+use crate::max::{OrderState, Side};
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridMode {
+    Arithmetic,
+    Geometric,
+}
+
+impl FromStr for GridMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GridMode, String> {
+        match s {
+            "arithmetic" => Ok(GridMode::Arithmetic),
+            "geometric" => Ok(GridMode::Geometric),
+            _ => Err(format!("unknown grid mode: {}", s)),
+        }
+    }
+}
+
+/* what a grid does once the price leaves [lower_price, upper_price] */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakoutPolicy {
+    Keep,
+    Pause,
+    Cancel,
+    Close,
+    Recenter,
+    Trail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct RangeShift {
+    pub time: DateTime<Local>,
+    pub direction: ShiftDirection,
+    pub lower_price: Decimal,
+    pub upper_price: Decimal,
+}
+
+impl FromStr for BreakoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BreakoutPolicy, String> {
+        match s {
+            "keep" => Ok(BreakoutPolicy::Keep),
+            "pause" => Ok(BreakoutPolicy::Pause),
+            "cancel" => Ok(BreakoutPolicy::Cancel),
+            "close" => Ok(BreakoutPolicy::Close),
+            "recenter" => Ok(BreakoutPolicy::Recenter),
+            "trail" => Ok(BreakoutPolicy::Trail),
+            _ => Err(format!("unknown breakout policy: {}", s)),
+        }
+    }
+}
+
+/*
+ * every level cycles through the same states. levels entering with a buy
+ * start from Empty, levels entering with a sell start from Bought since
+ * they already hold the (seeded) base inventory:
+ *
+ *   Empty -> BuyPending -> Bought -> SellPending -> Empty
+ *
+ * PartiallyFilled, Cancelled and Error branch off the pending states and
+ * keep the order in flight so that it can be resumed. Error is only for
+ * submissions that may still succeed, an order MAX (or the market limits)
+ * rejects for good ends the level, see on_submit_rejected.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelState {
+    Empty,
+    BuyPending,
+    Bought,
+    SellPending,
+    PartiallyFilled,
+    Cancelled,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelOrder {
+    pub id: Option<u64>,
+    pub side: Side,
+    pub price: Decimal,
+    pub volume: Decimal,
+    pub executed_volume: Decimal,
+    /* assigned before the first submission, a retry reuses it to find the order */
+    pub client_oid: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GridLevel {
+    pub index: usize,
+    pub price: Decimal,
+    pub side: Side,
+    pub volume: Decimal,
+    pub active: bool,
+    pub state: LevelState,
+    pub order: Option<LevelOrder>,
+    pub filled_volume: Decimal,
+    pub counter_volume: Decimal,
+}
+
+impl GridLevel {
+    fn new(index: usize, price: Decimal, volume: Decimal) -> GridLevel {
+        GridLevel {
+            index,
+            price,
+            side: Side::Buy,
+            volume,
+            active: false,
+            state: LevelState::Empty,
+            order: None,
+            filled_volume: Decimal::ZERO,
+            counter_volume: Decimal::ZERO,
+        }
+    }
+
+    /*
+     * long grids buy under the market and sell above it. short grids start
+     * from base inventory and only sell into rallies, so the levels under
+     * the market stay idle.
+     */
+    fn assign_side(&mut self, current_price: Decimal, long: bool) {
+        if long {
+            self.side = if self.price < current_price {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            self.active = true;
+        } else {
+            self.side = Side::Sell;
+            self.active = self.price > current_price;
+        }
+
+        self.state = match self.side {
+            Side::Buy => LevelState::Empty,
+            Side::Sell => LevelState::Bought,
+        };
+    }
+
+    fn transition(&mut self, symbol: &str, state: LevelState) {
+        if self.state != state {
+            info!(
+                "[{}] level {}: {:?} -> {:?}",
+                symbol, self.index, self.state, state
+            );
+            self.state = state;
+        }
+    }
+
+    pub fn order_id(&self) -> Option<u64> {
+        self.order.as_ref().and_then(|order| order.id)
+    }
+
+    /* an order of the level is resting on the exchange */
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.state,
+            LevelState::BuyPending | LevelState::SellPending | LevelState::PartiallyFilled
+        )
+    }
+
+    /* the level rests its own order first, then the counter-order once it fills */
+    fn new_order(&self, side: Side, profit_spread: Decimal) -> LevelOrder {
+        let counter = side != self.side;
+
+        LevelOrder {
+            id: None,
+            side,
+            price: match (counter, side) {
+                (false, _) => self.price,
+                (true, Side::Sell) => self.price + profit_spread,
+                (true, Side::Buy) => self.price - profit_spread,
+            },
+            volume: if counter {
+                self.counter_volume
+            } else {
+                self.volume
+            },
+            executed_volume: Decimal::ZERO,
+            client_oid: None,
+        }
+    }
+
+    /* the order to place in the current state, if the level needs one */
+    pub fn next_order(&self, profit_spread: Decimal) -> Option<LevelOrder> {
+        match self.state {
+            LevelState::Empty => Some(self.new_order(Side::Buy, profit_spread)),
+            LevelState::Bought => Some(self.new_order(Side::Sell, profit_spread)),
+            LevelState::Cancelled => self.order.as_ref().map(|order| LevelOrder {
+                id: None,
+                volume: order.volume - order.executed_volume,
+                executed_volume: Decimal::ZERO,
+                client_oid: None,
+                ..order.clone()
+            }),
+            /* the failed submission may have landed, it is sent again under the same client_oid */
+            LevelState::Error => self.order.clone(),
+            _ => None,
+        }
+    }
+
+    /* base inventory the level holds or has locked in a sell order */
+    pub fn base_held(&self) -> Decimal {
+        match (self.state, &self.order) {
+            (LevelState::Bought, _) => self.new_order(Side::Sell, Decimal::ZERO).volume,
+            (_, Some(order)) if order.side == Side::Sell => order.volume - order.executed_volume,
+            (_, Some(order)) => self.filled_volume + order.executed_volume,
+            _ => Decimal::ZERO,
+        }
+    }
+
+    pub fn on_submitted(&mut self, symbol: &str, mut order: LevelOrder, id: u64) {
+        let state = match order.side {
+            Side::Buy => LevelState::BuyPending,
+            Side::Sell => LevelState::SellPending,
+        };
+
+        order.id = Some(id);
+        self.order = Some(order);
+        self.transition(symbol, state);
+    }
+
+    pub fn on_submit_failed(&mut self, symbol: &str, order: LevelOrder) {
+        self.order = Some(order);
+        self.transition(symbol, LevelState::Error);
+    }
+
+    /*
+     * the order can never be placed. what the level filled before is settled
+     * as a fill so its counter-order follows, otherwise the level goes back to
+     * the state before the order and stops trading.
+     */
+    pub fn on_submit_rejected(
+        &mut self,
+        symbol: &str,
+        mut order: LevelOrder,
+        profit_spread: Decimal,
+        long: bool,
+    ) {
+        if self.filled_volume > Decimal::ZERO {
+            order.executed_volume = Decimal::ZERO;
+            self.order = Some(order);
+            self.on_filled(symbol, profit_spread, long);
+            return;
+        }
+
+        warn!(
+            "[{}] level {}: {} {} @ {} is rejected, disabling the level",
+            symbol, self.index, order.side, order.volume, order.price
+        );
+        self.order = None;
+        self.active = false;
+        let state = match order.side {
+            Side::Buy => LevelState::Empty,
+            Side::Sell => LevelState::Bought,
+        };
+        self.transition(symbol, state);
+    }
+
+    /* apply the order state and executed volume reported by the exchange */
+    pub fn on_order_update(
+        &mut self,
+        symbol: &str,
+        state: OrderState,
+        executed_volume: Decimal,
+        profit_spread: Decimal,
+        long: bool,
+    ) {
+        match self.order.as_mut() {
+            Some(order) => order.executed_volume = executed_volume,
+            None => return,
+        }
+
+        match state {
+            OrderState::Wait if executed_volume > Decimal::ZERO => {
+                self.transition(symbol, LevelState::PartiallyFilled)
+            }
+            OrderState::Done => self.on_filled(symbol, profit_spread, long),
+            OrderState::Cancel => self.on_cancelled(symbol),
+            _ => {}
+        }
+    }
+
+    fn on_filled(&mut self, symbol: &str, profit_spread: Decimal, long: bool) {
+        let order = self.order.take().unwrap();
+        let filled_volume = self.filled_volume + order.executed_volume;
+        self.filled_volume = Decimal::ZERO;
+
+        info!(
+            "[{}] level {}: {} {} @ {} filled",
+            symbol, self.index, order.side, filled_volume, order.price
+        );
+
+        let state = match order.side {
+            Side::Buy => LevelState::Bought,
+            Side::Sell => LevelState::Empty,
+        };
+
+        /* the counter-order trades back what the level's own order filled */
+        if order.side == self.side {
+            match self.counter_volume_for(filled_volume, profit_spread, long) {
+                Some(volume) => self.counter_volume = volume,
+                None => {
+                    warn!(
+                        "[{}] level {}: counter price {} - {} is not positive, disabling the level",
+                        symbol, self.index, self.price, profit_spread
+                    );
+                    self.active = false;
+                }
+            }
+        }
+
+        self.transition(symbol, state);
+    }
+
+    /* keep what was filled so far, the remainder is placed again later */
+    pub fn on_cancelled(&mut self, symbol: &str) {
+        let order = match self.order.as_mut() {
+            Some(order) => order,
+            None => return,
+        };
+
+        self.filled_volume += order.executed_volume;
+        order.volume -= order.executed_volume;
+        order.executed_volume = Decimal::ZERO;
+        order.id = None;
+
+        self.transition(symbol, LevelState::Cancelled);
+    }
+
+    /*
+     * long grids trade back only the quote amount they spent (or received),
+     * so the spread accrues in base currency. short grids trade back the
+     * same volume and keep the spread in quote currency.
+     */
+    pub fn counter_volume_for(
+        &self,
+        executed_volume: Decimal,
+        profit_spread: Decimal,
+        long: bool,
+    ) -> Option<Decimal> {
+        let counter_price = match self.side {
+            Side::Buy => self.price + profit_spread,
+            Side::Sell => self.price - profit_spread,
+        };
+
+        /* a sell level at or below profit_spread has no price to buy back at */
+        if counter_price <= Decimal::ZERO {
+            return None;
+        }
+
+        if !long {
+            return Some(executed_volume);
+        }
+
+        Some(executed_volume * self.price / counter_price)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub symbol: String,
+    pub mode: GridMode,
+    pub long: bool,
+    pub lower_price: Decimal,
+    pub upper_price: Decimal,
+    pub grid_number: u32,
+    pub quantity: Decimal,
+    pub levels: Vec<GridLevel>,
+    pub seed_orders: Vec<u64>,
+    pub paused: bool,
+    pub stopped: bool,
+    pub shifts: Vec<RangeShift>,
+    /* the last seeding failed part way and is resumed once its orders settle */
+    pub needs_seed: bool,
+    /* start time of the grid in seconds, tells its client_oids apart from earlier runs */
+    pub generation: i64,
+    sequence: u32,
+}
+
+fn grid_levels(
+    mode: GridMode,
+    lower_price: Decimal,
+    upper_price: Decimal,
+    grid_number: u32,
+    quantity: Decimal,
+) -> Vec<GridLevel> {
+    let prices = match mode {
+        GridMode::Arithmetic => arithmetic_prices(lower_price, upper_price, grid_number),
+        GridMode::Geometric => geometric_prices(lower_price, upper_price, grid_number),
+    };
+
+    prices
+        .into_iter()
+        .enumerate()
+        .map(|(index, price)| GridLevel::new(index, price, quantity))
+        .collect()
+}
+
+/* evenly spaced prices from lower to upper, both bounds included */
+pub fn arithmetic_prices(
+    lower_price: Decimal,
+    upper_price: Decimal,
+    grid_number: u32,
+) -> Vec<Decimal> {
+    let step = (upper_price - lower_price) / Decimal::from(grid_number);
+
+    let mut prices: Vec<Decimal> = (0..grid_number)
+        .map(|i| lower_price + step * Decimal::from(i))
+        .collect();
+
+    /* the step is rounded, pin the last level to the upper bound */
+    prices.push(upper_price);
+
+    prices
+}
+
+/* prices with a constant ratio between neighbours, both bounds included */
+pub fn geometric_prices(
+    lower_price: Decimal,
+    upper_price: Decimal,
+    grid_number: u32,
+) -> Vec<Decimal> {
+    let ratio = (upper_price / lower_price)
+        .to_f64()
+        .unwrap()
+        .powf(1.0 / grid_number as f64);
+
+    let mut prices: Vec<Decimal> = (0..grid_number)
+        .map(|i| lower_price * Decimal::from_f64(ratio.powi(i as i32)).unwrap())
+        .collect();
+
+    /* pin the last level to the upper bound to avoid floating point drift */
+    prices.push(upper_price);
+
+    prices
+}
+
+impl Grid {
+    pub fn new(
+        symbol: &str,
+        mode: GridMode,
+        long: bool,
+        lower_price: Decimal,
+        upper_price: Decimal,
+        grid_number: u32,
+        quantity: Decimal,
+    ) -> Grid {
+        let levels = grid_levels(mode, lower_price, upper_price, grid_number, quantity);
+
+        Grid {
+            symbol: symbol.into(),
+            mode,
+            long,
+            lower_price,
+            upper_price,
+            grid_number,
+            quantity,
+            levels,
+            seed_orders: Vec::new(),
+            paused: false,
+            stopped: false,
+            shifts: Vec::new(),
+            needs_seed: false,
+            generation: Local::now().timestamp(),
+            sequence: 0,
+        }
+    }
+
+    /* number of the next order placed by the grid, never reused within a generation */
+    pub fn next_sequence(&mut self) -> u32 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    pub fn layout(&mut self, current_price: Decimal) {
+        for level in self.levels.iter_mut() {
+            level.assign_side(current_price, self.long);
+        }
+    }
+
+    pub fn in_range(&self, price: Decimal) -> bool {
+        price >= self.lower_price && price <= self.upper_price
+    }
+
+    /*
+     * move the range so that it is centered on the current price while
+     * keeping its width (arithmetic) or its ratio (geometric). the caller
+     * must cancel the resting orders first since the levels are rebuilt.
+     * returns false if the new range would drop to profit_spread or below,
+     * where the lowest levels have no price to buy back at.
+     */
+    pub fn recenter(&mut self, current_price: Decimal, profit_spread: Decimal) -> bool {
+        let (lower_price, upper_price) = match self.mode {
+            GridMode::Arithmetic => {
+                let half_width = (self.upper_price - self.lower_price) / Decimal::TWO;
+                (current_price - half_width, current_price + half_width)
+            }
+            GridMode::Geometric => {
+                let ratio = (self.upper_price / self.lower_price).to_f64().unwrap();
+                let half_ratio = Decimal::from_f64(ratio.sqrt()).unwrap();
+                (current_price / half_ratio, current_price * half_ratio)
+            }
+        };
+
+        if lower_price <= profit_spread.max(Decimal::ZERO) {
+            return false;
+        }
+
+        self.lower_price = lower_price;
+        self.upper_price = upper_price;
+        self.levels = grid_levels(
+            self.mode,
+            lower_price,
+            upper_price,
+            self.grid_number,
+            self.quantity,
+        );
+        self.layout(current_price);
+
+        true
+    }
+
+    /*
+     * move the window by one level: drop the furthest level on the opposite
+     * side and add a new one in the breakout direction. returns the dropped
+     * level so the caller can cancel its order, or None if the range cannot
+     * move any further down, i.e. the new level would be at or below profit_spread.
+     */
+    pub fn shift(
+        &mut self,
+        direction: ShiftDirection,
+        current_price: Decimal,
+        profit_spread: Decimal,
+    ) -> Option<GridLevel> {
+        let first = self.levels[0].price;
+        let second = self.levels[1].price;
+        let last = self.levels[self.levels.len() - 1].price;
+
+        let removed = match direction {
+            ShiftDirection::Up => {
+                let price = match self.mode {
+                    GridMode::Arithmetic => last + (second - first),
+                    GridMode::Geometric => last * (second / first),
+                };
+
+                let mut level = GridLevel::new(0, price, self.quantity);
+                level.assign_side(current_price, self.long);
+                self.levels.push(level);
+                self.levels.remove(0)
+            }
+            ShiftDirection::Down => {
+                let price = match self.mode {
+                    GridMode::Arithmetic => first - (second - first),
+                    GridMode::Geometric => first / (second / first),
+                };
+                if price <= profit_spread.max(Decimal::ZERO) {
+                    return None;
+                }
+
+                let mut level = GridLevel::new(0, price, self.quantity);
+                level.assign_side(current_price, self.long);
+                self.levels.insert(0, level);
+                self.levels.pop().unwrap()
+            }
+        };
+
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            level.index = index;
+        }
+
+        self.lower_price = self.levels[0].price;
+        self.upper_price = self.levels[self.levels.len() - 1].price;
+        self.shifts.push(RangeShift {
+            time: Local::now(),
+            direction,
+            lower_price: self.lower_price,
+            upper_price: self.upper_price,
+        });
+
+        Some(removed)
+    }
+
+    pub fn buy_levels(&self) -> impl Iterator<Item = &GridLevel> {
+        self.levels
+            .iter()
+            .filter(|level| level.active && level.side == Side::Buy)
+    }
+
+    pub fn sell_levels(&self) -> impl Iterator<Item = &GridLevel> {
+        self.levels
+            .iter()
+            .filter(|level| level.active && level.side == Side::Sell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn arithmetic_prices_include_both_bounds() {
+        let prices = arithmetic_prices(dec!(100), dec!(200), 4);
+
+        assert_eq!(
+            prices,
+            vec![dec!(100), dec!(125), dec!(150), dec!(175), dec!(200)]
+        );
+    }
+
+    #[test]
+    fn arithmetic_prices_keep_a_constant_step() {
+        let prices = arithmetic_prices(dec!(0.5), dec!(0.8), 7);
+
+        assert_eq!(prices.len(), 8);
+        assert_eq!(prices[0], dec!(0.5));
+        assert_eq!(prices[7], dec!(0.8));
+
+        let step = prices[1] - prices[0];
+        for pair in prices.windows(2) {
+            assert!((pair[1] - pair[0] - step).abs() < dec!(0.0000000001));
+        }
+    }
+
+    #[test]
+    fn geometric_prices_include_both_bounds() {
+        let prices = geometric_prices(dec!(100), dec!(1600), 4);
+
+        assert_eq!(prices.len(), 5);
+        assert_eq!(prices[0], dec!(100));
+        assert_eq!(prices[4], dec!(1600));
+
+        for (price, expected) in prices.iter().zip([100, 200, 400, 800, 1600]) {
+            assert!((*price - Decimal::from(expected)).abs() < dec!(0.000001));
+        }
+    }
+
+    #[test]
+    fn geometric_prices_keep_a_constant_ratio() {
+        let prices = geometric_prices(dec!(0.03), dec!(0.05), 9);
+
+        assert_eq!(prices.len(), 10);
+        assert_eq!(prices[0], dec!(0.03));
+        assert_eq!(prices[9], dec!(0.05));
+
+        let ratio = prices[1] / prices[0];
+        for pair in prices.windows(2) {
+            assert!(pair[1] > pair[0]);
+            assert!((pair[1] / pair[0] - ratio).abs() < dec!(0.000000001));
+        }
+    }
+
+    fn buy_level() -> GridLevel {
+        let mut level = GridLevel::new(0, dec!(100), dec!(1));
+        level.assign_side(dec!(110), true);
+        level
+    }
+
+    #[test]
+    fn level_fill_places_counter_order_and_rearms() {
+        let mut level = buy_level();
+        assert_eq!(level.side, Side::Buy);
+        assert_eq!(level.state, LevelState::Empty);
+
+        let order = level.next_order(dec!(10)).unwrap();
+        assert_eq!(
+            (order.side, order.price, order.volume),
+            (Side::Buy, dec!(100), dec!(1))
+        );
+        level.on_submitted("btcusdt", order, 1);
+        assert_eq!(level.state, LevelState::BuyPending);
+        assert!(level.next_order(dec!(10)).is_none());
+
+        level.on_order_update("btcusdt", OrderState::Done, dec!(1), dec!(10), true);
+        assert_eq!(level.state, LevelState::Bought);
+        assert_eq!(level.counter_volume, dec!(100) / dec!(110));
+
+        let counter = level.next_order(dec!(10)).unwrap();
+        assert_eq!(counter.side, Side::Sell);
+        assert_eq!(counter.price, dec!(110));
+        assert_eq!(counter.volume, dec!(100) / dec!(110));
+        level.on_submitted("btcusdt", counter, 2);
+        assert_eq!(level.state, LevelState::SellPending);
+
+        level.on_order_update(
+            "btcusdt",
+            OrderState::Done,
+            dec!(100) / dec!(110),
+            dec!(10),
+            true,
+        );
+        assert_eq!(level.state, LevelState::Empty);
+        assert!(level.order.is_none());
+
+        let order = level.next_order(dec!(10)).unwrap();
+        assert_eq!(
+            (order.side, order.price, order.volume),
+            (Side::Buy, dec!(100), dec!(1))
+        );
+    }
+
+    #[test]
+    fn level_partial_fill_then_cancel_resumes_the_remainder() {
+        let mut level = buy_level();
+        let mut order = level.next_order(dec!(10)).unwrap();
+        order.client_oid = Some("ccgt-1-1-0-1".to_string());
+        level.on_submitted("btcusdt", order, 1);
+
+        level.on_order_update("btcusdt", OrderState::Wait, dec!(0.4), dec!(10), true);
+        assert_eq!(level.state, LevelState::PartiallyFilled);
+        assert_eq!(level.base_held(), dec!(0.4));
+
+        level.on_order_update("btcusdt", OrderState::Cancel, dec!(0.4), dec!(10), true);
+        assert_eq!(level.state, LevelState::Cancelled);
+        assert_eq!(level.filled_volume, dec!(0.4));
+        assert_eq!(level.base_held(), dec!(0.4));
+
+        let remainder = level.next_order(dec!(10)).unwrap();
+        assert_eq!(remainder.side, Side::Buy);
+        assert_eq!(remainder.price, dec!(100));
+        assert_eq!(remainder.volume, dec!(0.6));
+        assert!(remainder.id.is_none());
+        assert!(remainder.client_oid.is_none());
+
+        level.on_submitted("btcusdt", remainder, 2);
+        level.on_order_update("btcusdt", OrderState::Done, dec!(0.6), dec!(10), true);
+        assert_eq!(level.state, LevelState::Bought);
+        assert_eq!(level.filled_volume, Decimal::ZERO);
+        assert_eq!(level.counter_volume, dec!(100) / dec!(110));
+    }
+
+    #[test]
+    fn level_error_resends_the_same_order() {
+        let mut level = buy_level();
+        let mut order = level.next_order(dec!(10)).unwrap();
+        order.client_oid = Some("ccgt-1-1-0-1".to_string());
+
+        level.on_submit_failed("btcusdt", order);
+        assert_eq!(level.state, LevelState::Error);
+        assert!(!level.is_pending());
+
+        let retry = level.next_order(dec!(10)).unwrap();
+        assert_eq!(retry.side, Side::Buy);
+        assert_eq!(retry.volume, dec!(1));
+        assert_eq!(retry.client_oid.as_deref(), Some("ccgt-1-1-0-1"));
+
+        level.on_submitted("btcusdt", retry, 1);
+        assert_eq!(level.state, LevelState::BuyPending);
+    }
+
+    #[test]
+    fn level_rejected_without_fills_is_disabled() {
+        let mut level = buy_level();
+        let order = level.next_order(dec!(10)).unwrap();
+
+        level.on_submit_rejected("btcusdt", order, dec!(10), true);
+        assert_eq!(level.state, LevelState::Empty);
+        assert!(!level.active);
+        assert!(level.order.is_none());
+    }
+
+    #[test]
+    fn counter_price_at_or_below_zero_has_no_volume() {
+        for long in [true, false] {
+            let mut level = GridLevel::new(0, dec!(5), dec!(1));
+            level.assign_side(dec!(1), long);
+            assert_eq!(level.side, Side::Sell);
+
+            assert!(level.counter_volume_for(dec!(1), dec!(5), long).is_none());
+            assert!(level.counter_volume_for(dec!(1), dec!(10), long).is_none());
+            assert!(level.counter_volume_for(dec!(1), dec!(4), long).is_some());
+        }
+    }
+
+    #[test]
+    fn short_grid_only_sells_above_the_market() {
+        let mut grid = Grid::new(
+            "btcusdt",
+            GridMode::Arithmetic,
+            false,
+            dec!(100),
+            dec!(200),
+            4,
+            dec!(1),
+        );
+        grid.layout(dec!(140));
+
+        assert!(grid.levels.iter().all(|level| level.side == Side::Sell));
+        assert!(grid
+            .levels
+            .iter()
+            .all(|level| level.state == LevelState::Bought));
+        assert_eq!(
+            grid.levels
+                .iter()
+                .map(|level| level.active)
+                .collect::<Vec<_>>(),
+            vec![false, false, true, true, true]
+        );
+        assert_eq!(grid.buy_levels().count(), 0);
+        assert_eq!(grid.sell_levels().count(), 3);
+    }
+
+    #[test]
+    fn short_grid_buys_back_the_same_volume() {
+        let mut level = GridLevel::new(0, dec!(150), dec!(1));
+        level.assign_side(dec!(140), false);
+
+        let order = level.next_order(dec!(10)).unwrap();
+        assert_eq!(
+            (order.side, order.price, order.volume),
+            (Side::Sell, dec!(150), dec!(1))
+        );
+        level.on_submitted("btcusdt", order, 1);
+        level.on_order_update("btcusdt", OrderState::Done, dec!(1), dec!(10), false);
+        assert_eq!(level.state, LevelState::Empty);
+        assert_eq!(level.counter_volume, dec!(1));
+
+        let counter = level.next_order(dec!(10)).unwrap();
+        assert_eq!(counter.side, Side::Buy);
+        assert_eq!(counter.price, dec!(140));
+        assert_eq!(counter.volume, dec!(1));
+
+        level.on_submitted("btcusdt", counter, 2);
+        level.on_order_update("btcusdt", OrderState::Done, dec!(1), dec!(10), false);
+        assert_eq!(level.state, LevelState::Bought);
+        assert_eq!(level.next_order(dec!(10)).unwrap().volume, dec!(1));
+    }
+
+    fn long_grid(mode: GridMode, lower_price: Decimal, upper_price: Decimal) -> Grid {
+        Grid::new("btcusdt", mode, true, lower_price, upper_price, 4, dec!(1))
+    }
+
+    fn level_prices(grid: &Grid) -> Vec<Decimal> {
+        grid.levels.iter().map(|level| level.price).collect()
+    }
+
+    #[test]
+    fn shift_up_adds_a_top_level_and_drops_the_bottom_one() {
+        let mut grid = long_grid(GridMode::Arithmetic, dec!(100), dec!(200));
+        grid.layout(dec!(150));
+
+        let removed = grid.shift(ShiftDirection::Up, dec!(210), dec!(10)).unwrap();
+        assert_eq!(removed.price, dec!(100));
+        assert_eq!(
+            level_prices(&grid),
+            vec![dec!(125), dec!(150), dec!(175), dec!(200), dec!(225)]
+        );
+        assert!(grid
+            .levels
+            .iter()
+            .enumerate()
+            .all(|(index, level)| level.index == index));
+        assert_eq!((grid.lower_price, grid.upper_price), (dec!(125), dec!(225)));
+
+        let added = grid.levels.last().unwrap();
+        assert!(added.active);
+        assert_eq!(added.side, Side::Sell);
+        assert_eq!(grid.shifts.len(), 1);
+        assert_eq!(grid.shifts[0].direction, ShiftDirection::Up);
+    }
+
+    #[test]
+    fn shift_down_adds_a_bottom_level_and_drops_the_top_one() {
+        let mut grid = long_grid(GridMode::Arithmetic, dec!(100), dec!(200));
+        grid.layout(dec!(150));
+
+        let removed = grid
+            .shift(ShiftDirection::Down, dec!(90), dec!(10))
+            .unwrap();
+        assert_eq!(removed.price, dec!(200));
+        assert_eq!(
+            level_prices(&grid),
+            vec![dec!(75), dec!(100), dec!(125), dec!(150), dec!(175)]
+        );
+        assert!(grid
+            .levels
+            .iter()
+            .enumerate()
+            .all(|(index, level)| level.index == index));
+        assert_eq!((grid.lower_price, grid.upper_price), (dec!(75), dec!(175)));
+
+        let added = grid.levels.first().unwrap();
+        assert!(added.active);
+        assert_eq!(added.side, Side::Buy);
+        assert_eq!(grid.shifts[0].direction, ShiftDirection::Down);
+    }
+
+    #[test]
+    fn shift_keeps_the_geometric_ratio() {
+        let mut grid = long_grid(GridMode::Geometric, dec!(100), dec!(1600));
+        grid.layout(dec!(500));
+
+        grid.shift(ShiftDirection::Up, dec!(2000), dec!(10))
+            .unwrap();
+        assert!((grid.upper_price - dec!(3200)).abs() < dec!(0.0001));
+
+        grid.shift(ShiftDirection::Down, dec!(50), dec!(10))
+            .unwrap();
+        grid.shift(ShiftDirection::Down, dec!(50), dec!(10))
+            .unwrap();
+        assert!((grid.lower_price - dec!(50)).abs() < dec!(0.0001));
+        assert!((grid.upper_price - dec!(800)).abs() < dec!(0.0001));
+    }
+
+    #[test]
+    fn shift_down_stops_above_profit_spread() {
+        let mut grid = long_grid(GridMode::Arithmetic, dec!(20), dec!(60));
+        grid.layout(dec!(40));
+
+        assert!(grid.shift(ShiftDirection::Down, dec!(5), dec!(5)).is_some());
+        assert_eq!(grid.lower_price, dec!(10));
+
+        assert!(grid.shift(ShiftDirection::Down, dec!(5), dec!(5)).is_none());
+        assert!(grid
+            .shift(ShiftDirection::Down, dec!(5), Decimal::ZERO)
+            .is_none());
+        assert_eq!(
+            level_prices(&grid),
+            vec![dec!(10), dec!(20), dec!(30), dec!(40), dec!(50)]
+        );
+        assert_eq!(grid.shifts.len(), 1);
+    }
+
+    #[test]
+    fn recenter_keeps_the_arithmetic_width() {
+        let mut grid = long_grid(GridMode::Arithmetic, dec!(100), dec!(200));
+        grid.layout(dec!(150));
+
+        assert!(grid.recenter(dec!(300), dec!(10)));
+        assert_eq!((grid.lower_price, grid.upper_price), (dec!(250), dec!(350)));
+        assert_eq!(
+            level_prices(&grid),
+            vec![dec!(250), dec!(275), dec!(300), dec!(325), dec!(350)]
+        );
+        assert_eq!(grid.buy_levels().count(), 2);
+        assert_eq!(grid.sell_levels().count(), 3);
+    }
+
+    #[test]
+    fn recenter_keeps_the_geometric_ratio() {
+        let mut grid = long_grid(GridMode::Geometric, dec!(100), dec!(400));
+        grid.layout(dec!(200));
+
+        assert!(grid.recenter(dec!(1000), dec!(10)));
+        assert!((grid.lower_price - dec!(500)).abs() < dec!(0.0001));
+        assert!((grid.upper_price - dec!(2000)).abs() < dec!(0.0001));
+        assert_eq!(grid.levels.len(), 5);
+        assert!((grid.levels[2].price - dec!(1000)).abs() < dec!(0.0001));
+    }
+
+    #[test]
+    fn recenter_refuses_a_range_at_or_below_zero() {
+        let mut grid = long_grid(GridMode::Arithmetic, dec!(100), dec!(200));
+        grid.layout(dec!(150));
+
+        assert!(!grid.recenter(dec!(40), Decimal::ZERO));
+        assert!(!grid.recenter(dec!(50), Decimal::ZERO));
+        assert!(!grid.recenter(dec!(55), dec!(10)));
+        assert_eq!((grid.lower_price, grid.upper_price), (dec!(100), dec!(200)));
+        assert_eq!(
+            level_prices(&grid),
+            vec![dec!(100), dec!(125), dec!(150), dec!(175), dec!(200)]
+        );
+    }
+}
@@ -1,22 +1,33 @@
+mod cli;
+mod error;
+mod grid;
+mod market;
+mod max;
+
 mod ccgt {
     extern crate yaml_rust;
 
-    use base64::encode as b64_encode;
-    use hmac::{Hmac, Mac, NewMac};
+    use crate::error::{Error, Result};
+    use crate::grid::{BreakoutPolicy, Grid, GridMode, LevelOrder, ShiftDirection};
+    use crate::market::Catalog;
+    use crate::max::{
+        self, Account, Candle, Depth, Endpoints, MaxClient, Order, OrderRequest, OrderState,
+        OrderType, Pages, Side, Ticker, Trade,
+    };
+    use chrono::{DateTime, Utc};
+    use futures_util::future::join_all;
     use log::{error, info, warn, LevelFilter};
-    use reqwest::header;
     use rust_decimal::prelude::*;
     use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
-    use serde::Serialize;
-    use sha2::Sha256;
+    use std::collections::HashMap;
     use std::env;
-    use std::fmt::Display;
     use std::io::Read;
     use std::io::Write;
     use std::str;
-    use std::time::{SystemTime, UNIX_EPOCH};
-    use yaml_rust::YamlLoader;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use yaml_rust::{Yaml, YamlLoader};
 
     #[derive(Debug)]
     #[allow(dead_code)]
@@ -39,35 +50,153 @@ mod ccgt {
         upper_price: Decimal,
         lower_price: Decimal,
         long: bool,
-    }
-
-    #[derive(Debug)]
-    #[allow(dead_code)]
-    struct Account {
-        currency: String,
-        balance: String,
-        locked: String,
-        stacked: String,
-        r#type: String,
-        fiat_currency: String,
-        fiat_balance: String,
+        grid_mode: GridMode,
+        breakout_policy: BreakoutPolicy,
+        post_only: bool,
+        group_id: u64,
     }
 
     pub struct GridTradeBot {
-        access_key: String,
-        secret_key: String,
+        client: MaxClient,
         risk_control: Vec<RiskControl>,
         trade_strategies: Vec<TradeStrategy>,
-        accounts: Vec<Account>,
+        accounts: Mutex<Vec<Account>>,
+        grids: HashMap<String, Grid>,
+        catalog: Catalog,
+        catalog_time: Option<Instant>,
+    }
+
+    /* price tolerance around the best quote for orders that must fill at once */
+    const MARKET_SLIPPAGE: Decimal = dec!(0.005);
+
+    /* MAX takes its fee out of the base a buy receives, inventory buys cover it with this margin */
+    const FEE_MARGIN: Decimal = dec!(0.003);
+
+    /* upper bound of orders sent in a single orders/multi request */
+    const MAX_BATCH_ORDERS: usize = 20;
+
+    /* items requested per page when walking order and trade history */
+    const HISTORY_PAGE_LIMIT: u64 = 100;
+
+    /* how often the market catalog (units, precision, minimum amounts) is refreshed */
+    const CATALOG_REFRESH: Duration = Duration::from_secs(3600);
+
+    /* unique id attached to orders placed outside a strategy, e.g. from the command line */
+    fn new_client_oid() -> String {
+        format!("ccgt-{:016x}", rand::random::<u64>())
     }
 
-    fn get_timestamp(time: SystemTime) -> u128 {
-        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap();
-        since_epoch.as_millis()
+    /* longest client_oid MAX accepts */
+    const MAX_CLIENT_OID_LEN: usize = 36;
+
+    /*
+     * id of an order placed by a strategy: ccgt-<group>-<generation>-<level>-<sequence>,
+     * hex except for the level, load_yaml checks that it fits MAX_CLIENT_OID_LEN.
+     * it is fixed before the first attempt, used to look the order up after an
+     * ambiguous failure and is the key joining the logs with the order and fill history.
+     */
+    fn strategy_client_oid(group_id: u64, generation: i64, level: &str, sequence: u32) -> String {
+        format!(
+            "ccgt-{:x}-{:x}-{}-{:x}",
+            group_id, generation, level, sequence
+        )
+    }
+
+    /*
+     * group of the orders placed by the strategy of a market, FNV-1a of the symbol
+     * so it stays the same across restarts without being configured
+     */
+    fn default_group_id(symbol: &str) -> u64 {
+        let mut hash: u32 = 0x811c9dc5;
+        for byte in symbol.bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+
+        (hash & 0x7fff_ffff) as u64
+    }
+
+    /* the first document of config.yaml, None if there is no config.yaml */
+    fn read_config() -> Result<Option<Yaml>> {
+        let mut file = match std::fs::File::open("config.yaml") {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return config_error(format!("unable to open config.yaml: {}", error)),
+        };
+
+        let mut yaml_str = String::new();
+
+        if let Err(error) = file.read_to_string(&mut yaml_str) {
+            return config_error(format!("unable to read config.yaml: {}", error));
+        }
+
+        let docs = match YamlLoader::load_from_str(&yaml_str) {
+            Ok(docs) => docs,
+            Err(error) => return config_error(format!("config.yaml: {}", error)),
+        };
+        match docs.into_iter().next() {
+            Some(doc) => Ok(Some(doc)),
+            None => config_error("config.yaml is empty".into()),
+        }
+    }
+
+    /* endpoints come from the environment, then config.yaml, then the MAX defaults */
+    fn load_endpoints(doc: Option<&Yaml>) -> Endpoints {
+        let mut endpoints = Endpoints::default();
+
+        if let Some(doc) = doc {
+            if let Some(url) = doc["endpoints"]["rest_url"].as_str() {
+                endpoints.rest_url = url.into();
+            }
+            if let Some(url) = doc["endpoints"]["ws_url"].as_str() {
+                endpoints.ws_url = url.into();
+            }
+        }
+
+        if let Ok(url) = env::var("MAX_REST_URL") {
+            endpoints.rest_url = url;
+        }
+        if let Ok(url) = env::var("MAX_WS_URL") {
+            endpoints.ws_url = url;
+        }
+
+        endpoints.rest_url = endpoints.rest_url.trim_end_matches('/').into();
+        endpoints
+    }
+
+    fn config_error<T>(message: String) -> Result<T> {
+        Err(Error::Config(message))
+    }
+
+    fn config_str<'a>(node: &'a Yaml, key: &str) -> Result<&'a str> {
+        match node[key].as_str() {
+            Some(value) => Ok(value),
+            None => config_error(format!("{} must be a string", key)),
+        }
+    }
+
+    fn config_bool(node: &Yaml, key: &str) -> Result<bool> {
+        match node[key].as_bool() {
+            Some(value) => Ok(value),
+            None => config_error(format!("{} must be a boolean", key)),
+        }
+    }
+
+    fn config_decimal(node: &Yaml, key: &str) -> Result<Decimal> {
+        /* as_f64 only reads reals, integers like grid_number: 10 are their own variant */
+        let value = match &node[key] {
+            Yaml::Integer(value) => Some(Decimal::from(*value)),
+            value => value.as_f64().and_then(Decimal::from_f64),
+        };
+
+        match value {
+            Some(value) => Ok(value),
+            None => config_error(format!("{} must be a number", key)),
+        }
     }
 
     impl GridTradeBot {
-        pub fn new() -> GridTradeBot {
+        pub fn new() -> Result<GridTradeBot> {
             std::env::set_var("RUST_LOG", "info");
             env_logger::Builder::new()
                 .format(|buf, record| {
@@ -85,533 +214,1583 @@ mod ccgt {
 
             dotenv::dotenv().ok();
 
-            GridTradeBot {
-                access_key: env::var("MAX_API_KEY").unwrap(),
-                secret_key: env::var("MAX_API_SECRET").unwrap(),
+            let access_key = match env::var("MAX_API_KEY") {
+                Ok(access_key) => access_key,
+                Err(_) => return config_error("MAX_API_KEY is not set".into()),
+            };
+            let secret_key = match env::var("MAX_API_SECRET") {
+                Ok(secret_key) => secret_key,
+                Err(_) => return config_error("MAX_API_SECRET is not set".into()),
+            };
+
+            /* operator actions skip load_yaml, they must still reach the configured exchange */
+            let endpoints = load_endpoints(read_config()?.as_ref());
+
+            Ok(GridTradeBot {
+                client: MaxClient::new(endpoints, access_key, secret_key),
                 risk_control: Vec::new(),
                 trade_strategies: Vec::new(),
-                accounts: Vec::new(),
-            }
+                accounts: Mutex::new(Vec::new()),
+                grids: HashMap::new(),
+                catalog: Catalog::default(),
+                catalog_time: None,
+            })
         }
 
-        pub fn load_yaml(&mut self) {
-            let mut file = match std::fs::File::open("config.yaml") {
-                Ok(file) => file,
-                Err(_error) => {
-                    error!("config.yaml is not found");
-                    std::process::exit(1);
-                }
+        pub fn load_yaml(&mut self) -> Result<()> {
+            let config = match read_config()? {
+                Some(config) => config,
+                None => return config_error("config.yaml is not found".into()),
             };
+            let doc = &config;
 
-            let mut yaml_str = String::new();
-
-            file.read_to_string(&mut yaml_str)
-                .expect("Unable to read file");
-
-            let docs = YamlLoader::load_from_str(&yaml_str).unwrap();
-            let doc = &docs[0];
-
-            assert_eq!(doc["strategies"][0]["symbol"].as_str().unwrap(), "dogetwd");
+            self.client.set_endpoints(load_endpoints(Some(doc)));
+            let endpoints = self.client.endpoints();
+            info!(
+                "MAX endpoints: rest={}, ws={}",
+                endpoints.rest_url, endpoints.ws_url
+            );
 
-            for risk_ctrl in doc["risk_control"].as_vec().unwrap() {
-                let symbol = risk_ctrl["symbol"].as_str().unwrap().into();
-                let min_quote_balance = risk_ctrl["min_quote_balance"].as_f64().unwrap();
-                let max_base_asset_balance = risk_ctrl["max_base_asset_balance"].as_f64().unwrap();
-                let min_base_asset_balance = risk_ctrl["min_base_asset_balance"].as_f64().unwrap();
-                let max_order_amount = risk_ctrl["max_order_amount"].as_f64().unwrap();
+            let risk_controls = match doc["risk_control"].as_vec() {
+                Some(risk_controls) => risk_controls,
+                None => return config_error("risk_control is not found".into()),
+            };
 
+            for risk_ctrl in risk_controls {
                 let new_risk_ctrl = RiskControl {
-                    symbol: symbol,
-                    min_quote_balance: Decimal::from_f64(min_quote_balance).unwrap(),
-                    max_base_asset_balance: Decimal::from_f64(max_base_asset_balance).unwrap(),
-                    min_base_asset_balance: Decimal::from_f64(min_base_asset_balance).unwrap(),
-                    max_order_amount: Decimal::from_f64(max_order_amount).unwrap(),
+                    symbol: config_str(risk_ctrl, "symbol")?.into(),
+                    min_quote_balance: config_decimal(risk_ctrl, "min_quote_balance")?,
+                    max_base_asset_balance: config_decimal(risk_ctrl, "max_base_asset_balance")?,
+                    min_base_asset_balance: config_decimal(risk_ctrl, "min_base_asset_balance")?,
+                    max_order_amount: config_decimal(risk_ctrl, "max_order_amount")?,
                 };
                 //println!("{:?}", new_risk_ctrl);
 
+                if new_risk_ctrl.max_order_amount <= Decimal::ZERO {
+                    return config_error("max_order_amount must be positive".into());
+                }
+
                 self.risk_control.push(new_risk_ctrl);
             }
 
-            for strategy in doc["strategies"].as_vec().unwrap() {
-                let enabled = strategy["enabled"].as_bool().unwrap();
-                let symbol = strategy["symbol"].as_str().unwrap().into();
-                let quantity = strategy["quantity"].as_f64().unwrap();
-                let grid_number = strategy["grid_number"].as_f64().unwrap();
-                let profit_spread = strategy["profit_spread"].as_f64().unwrap();
-                let upper_price = strategy["upper_price"].as_f64().unwrap();
-                let lower_price = strategy["lower_price"].as_f64().unwrap();
-                let long = strategy["long"].as_bool().unwrap();
+            let strategies = match doc["strategies"].as_vec() {
+                Some(strategies) => strategies,
+                None => return config_error("strategies is not found".into()),
+            };
+
+            for strategy in strategies {
+                let grid_mode = match strategy["grid_mode"]
+                    .as_str()
+                    .unwrap_or("arithmetic")
+                    .parse::<GridMode>()
+                {
+                    Ok(grid_mode) => grid_mode,
+                    Err(message) => return config_error(message),
+                };
+                let breakout_policy = match strategy["breakout_policy"]
+                    .as_str()
+                    .unwrap_or("keep")
+                    .parse::<BreakoutPolicy>()
+                {
+                    Ok(breakout_policy) => breakout_policy,
+                    Err(message) => return config_error(message),
+                };
+
+                let symbol = config_str(strategy, "symbol")?;
+                let group_id = match &strategy["group_id"] {
+                    Yaml::BadValue => default_group_id(symbol),
+                    /* kept to 32 bits so the client_oids stay within the MAX limit */
+                    node => match node.as_i64().and_then(|id| u32::try_from(id).ok()) {
+                        Some(id) if id > 0 => id as u64,
+                        _ => {
+                            return config_error(format!(
+                                "group_id must be a positive integer up to {}",
+                                u32::MAX
+                            ))
+                        }
+                    },
+                };
 
                 let new_strategy = TradeStrategy {
-                    enabled: enabled,
-                    symbol: symbol,
-                    quantity: Decimal::from_f64(quantity).unwrap(),
-                    grid_number: Decimal::from_f64(grid_number).unwrap(),
-                    profit_spread: Decimal::from_f64(profit_spread).unwrap(),
-                    upper_price: Decimal::from_f64(upper_price).unwrap(),
-                    lower_price: Decimal::from_f64(lower_price).unwrap(),
-                    long: long,
+                    enabled: config_bool(strategy, "enabled")?,
+                    symbol: symbol.into(),
+                    quantity: config_decimal(strategy, "quantity")?,
+                    grid_number: config_decimal(strategy, "grid_number")?,
+                    profit_spread: config_decimal(strategy, "profit_spread")?,
+                    upper_price: config_decimal(strategy, "upper_price")?,
+                    lower_price: config_decimal(strategy, "lower_price")?,
+                    long: config_bool(strategy, "long")?,
+                    grid_mode,
+                    breakout_policy,
+                    post_only: strategy["post_only"].as_bool().unwrap_or(true),
+                    group_id,
                 };
                 //println!("{:?}", new_strategy);
 
+                if new_strategy.upper_price <= new_strategy.lower_price {
+                    return config_error("upper_price must be greater than lower_price".into());
+                }
+
+                if new_strategy.grid_number < Decimal::ONE
+                    || !new_strategy.grid_number.fract().is_zero()
+                    || new_strategy.grid_number.to_u32().is_none()
+                {
+                    return config_error("grid_number must be a positive integer".into());
+                }
+
+                /* the longest client_oid the strategy can produce */
+                let client_oid = strategy_client_oid(
+                    new_strategy.group_id,
+                    chrono::Local::now().timestamp(),
+                    &new_strategy.grid_number.to_string(),
+                    u32::MAX,
+                );
+                if client_oid.len() > MAX_CLIENT_OID_LEN {
+                    return config_error(format!(
+                        "[{}] client_oid {} exceeds {} characters, lower grid_number or group_id",
+                        new_strategy.symbol, client_oid, MAX_CLIENT_OID_LEN
+                    ));
+                }
+
+                if new_strategy.profit_spread <= Decimal::ZERO {
+                    return config_error("profit_spread must be positive".into());
+                }
+
+                /* the counter-order of the lowest sell level must have a positive price */
+                if new_strategy.profit_spread >= new_strategy.lower_price {
+                    return config_error("profit_spread must be less than lower_price".into());
+                }
+
+                if grid_mode == GridMode::Geometric && new_strategy.lower_price <= Decimal::ZERO {
+                    return config_error("lower_price must be positive for geometric grids".into());
+                }
+
                 self.trade_strategies.push(new_strategy);
             }
+
+            /* every enabled strategy needs its own market and risk control */
+            for strategy in self.trade_strategies.iter().filter(|s| s.enabled) {
+                let count = self
+                    .trade_strategies
+                    .iter()
+                    .filter(|s| s.enabled && s.symbol == strategy.symbol)
+                    .count();
+                if count > 1 {
+                    return config_error(format!(
+                        "[{}] more than one strategy is enabled",
+                        strategy.symbol
+                    ));
+                }
+
+                self.risk_control_of(&strategy.symbol)?;
+
+                /* the group is all that tells the orders of two strategies apart */
+                if let Some(other) = self.trade_strategies.iter().find(|s| {
+                    s.enabled && s.symbol != strategy.symbol && s.group_id == strategy.group_id
+                }) {
+                    return config_error(format!(
+                        "[{}] group_id {} is also used by {}",
+                        strategy.symbol, strategy.group_id, other.symbol
+                    ));
+                }
+            }
+
+            Ok(())
         }
 
-        pub fn get_server_time(&self) -> i32 {
-            let respond = reqwest::blocking::get("https://max-api.maicoin.com/api/v2/timestamp")
-                .unwrap()
-                .json::<i32>()
-                .unwrap();
-            //println!("server time: {:#?}", respond);
+        pub fn markets(&self) -> Vec<String> {
+            self.trade_strategies
+                .iter()
+                .filter(|strategy| strategy.enabled)
+                .map(|strategy| strategy.symbol.clone())
+                .collect()
+        }
 
-            respond
-        }
-
-        pub fn get_ticker_info(&self, market: &str) {
-            let respond = reqwest::blocking::get(format!(
-                "https://max-api.maicoin.com/api/v2/tickers/{}",
-                market
-            ))
-            .unwrap()
-            .json::<serde_json::Value>()
-            .unwrap();
-            println!("ticker: {:#?}", respond);
-        }
-
-        pub fn build_auth_client(
-            &mut self,
-            api_path: &str,
-            params: &String,
-            payload: &String,
-        ) -> (reqwest::blocking::Client, String) {
-            /* generate the signature */
-            let mut signed_key =
-                Hmac::<Sha256>::new_from_slice(self.secret_key.as_bytes()).unwrap();
-            signed_key.update(payload.as_bytes());
-            let signature = hex::encode(signed_key.finalize().into_bytes());
-
-            /* setup request header */
-            let mut header = header::HeaderMap::new();
-            header.insert(
-                "X-MAX-ACCESSKEY",
-                header::HeaderValue::from_str(&self.access_key).unwrap(),
-            );
-            header.insert(
-                "X-MAX-PAYLOAD",
-                header::HeaderValue::from_str(&payload).unwrap(),
-            );
-            header.insert(
-                "X-MAX-SIGNATURE",
-                header::HeaderValue::from_str(&signature).unwrap(),
-            );
-            header.insert(
-                "Content-Type",
-                header::HeaderValue::from_static("application/json"),
-            );
+        /* group_id tagging the orders of the strategy of a market */
+        pub fn group_id_of(&self, market: &str) -> u64 {
+            self.strategy_of(market).group_id
+        }
 
-            /* setup request content */
-            let request = format!("https://max-api.maicoin.com/{}?{}", api_path, params);
-            //println!("{}", request);
-
-            /* create request sender with the pre-defined header */
-            let client = reqwest::blocking::Client::builder()
-                .default_headers(header)
-                .build()
-                .unwrap();
-
-            (client, request)
-        }
-
-        fn option_to_string<T: Display>(&self, option: &Option<T>) -> String {
-            match option {
-                None => "".to_string(),
-                Some(v) => v.to_string(),
-            }
-        }
-
-        pub fn submit_order(&mut self) {
-            let api_path = "/api/v2/orders";
-
-            /* get milliseconds time of UNIX epoch time since 1970 */
-            let timestamp = get_timestamp(SystemTime::now());
-
-            #[derive(Serialize)]
-            struct Payload {
-                nonce: String,
-                market: String,
-                side: String,
-                volume: Decimal,
-                price: Decimal,
-                client_oid: Option<String>,
-                stop_price: Option<Decimal>,
-                ord_type: String,
-                group_id: Option<u64>,
-                path: String,
-            }
-
-            /* prepare payload data */
-            let payload_raw = Payload {
-                nonce: timestamp.to_string(),
-                market: "dogetwd".into(),
-                side: "buy".into(),
-                volume: dec!(100000.0),
-                price: dec!(1),
-                client_oid: Some("max_rs_api_case_create_order".into()),
-                stop_price: None,
-                ord_type: "limit".into(),
-                group_id: None,
-                path: api_path.into(),
-            };
+        /* also updates the clock offset applied to the nonces */
+        pub async fn get_server_time(&self) -> Result<i64> {
+            let respond = self.client.sync_clock().await?;
+            //println!("server time: {:#?}", respond);
 
-            let params = format!(
-                "nonce={}&market={}&side={}&volume={}&\
-                 price={}&client_oid={}&stop_price={}&\
-                 ord_type={}&group_id={}",
-                payload_raw.nonce,
-                payload_raw.market,
-                payload_raw.side,
-                payload_raw.volume.to_string(),
-                payload_raw.price.to_string(),
-                self.option_to_string(&payload_raw.client_oid),
-                self.option_to_string(&payload_raw.stop_price),
-                payload_raw.ord_type,
-                self.option_to_string(&payload_raw.group_id),
-            );
-            //println!("params: {}", params);
+            Ok(respond)
+        }
 
-            /* pack the payload with Base64 format */
-            let payload_json_b64 =
-                b64_encode(serde_json::to_string(&payload_raw).unwrap().as_bytes());
-            //println!("json: {}", serde_json::to_string(&payload_raw).unwrap());
+        pub async fn sync_catalog(&mut self) -> Result<()> {
+            let markets = self.client.send(&max::GetMarkets {}).await?;
+            let currencies = self.client.send(&max::GetCurrencies {}).await?;
 
-            /* build client embedded with authorization info */
-            let (client, request) = self.build_auth_client(&api_path, &params, &payload_json_b64);
+            self.catalog = Catalog::new(markets, currencies);
+            self.catalog_time = Some(Instant::now());
 
-            /* send the request and wait for the respond */
-            let respond = client
-                .post(request)
-                .send()
-                .unwrap()
-                .json::<serde_json::Value>()
-                .unwrap();
-            //println!("result: {:?}", respond);
-
-            if respond["error"] != serde_json::Value::Null {
-                error!(
-                    "Failed to submit the order: {}",
-                    respond["error"]["message"]
+            Ok(())
+        }
+
+        /* refuse to trade a strategy whose market is not listed */
+        pub fn check_markets(&self) -> Result<()> {
+            for symbol in self.markets() {
+                let market = self.catalog.market(&symbol)?;
+                info!(
+                    "[{}] base={}, quote={}, min amount {} {} / {} {}",
+                    symbol,
+                    market.base_unit,
+                    market.quote_unit,
+                    market.min_base_amount,
+                    market.base_unit,
+                    market.min_quote_amount,
+                    market.quote_unit
                 );
             }
+
+            Ok(())
+        }
+
+        pub async fn get_ticker_info(&self, market: &str) -> Result<Ticker> {
+            let respond = self
+                .client
+                .send(&max::GetTicker {
+                    market: market.into(),
+                })
+                .await?;
+
+            Ok(respond)
+        }
+
+        pub async fn get_depth(&self, market: &str, limit: Option<u64>) -> Result<Depth> {
+            let respond = self
+                .client
+                .send(&max::GetDepth {
+                    market: market.into(),
+                    limit,
+                })
+                .await?;
+
+            Ok(respond)
+        }
+
+        /* the latest candles of period minutes, or those starting at since */
+        pub async fn get_candles(
+            &self,
+            market: &str,
+            period: u32,
+            limit: Option<u64>,
+            since: Option<DateTime<Utc>>,
+        ) -> Result<Vec<Candle>> {
+            let respond = self
+                .client
+                .send(&max::GetCandles {
+                    market: market.into(),
+                    limit,
+                    period: Some(period),
+                    timestamp: since.map(|since| since.timestamp()),
+                })
+                .await?;
+
+            Ok(respond)
         }
 
-        pub fn delete_order(&mut self) {
-            let api_path = "/api/v2/order/delete";
+        /* the latest public trades of the market, newest first */
+        pub async fn get_market_trades(
+            &self,
+            market: &str,
+            limit: Option<u64>,
+        ) -> Result<Vec<Trade>> {
+            let respond = self
+                .client
+                .send(&max::GetTrades {
+                    market: market.into(),
+                    timestamp: None,
+                    from: None,
+                    to: None,
+                    order_by: Some("desc".into()),
+                    limit,
+                })
+                .await?;
 
-            /* get milliseconds time of UNIX epoch time since 1970 */
-            let timestamp = get_timestamp(SystemTime::now());
+            Ok(respond)
+        }
 
-            #[derive(Serialize)]
-            struct Payload {
-                nonce: String,
-                id: Option<u64>,
-                client_oid: Option<String>,
-                path: String,
+        /* fit the order to the market precision and minimum amounts */
+        fn fit_order(&self, market: &str, mut order: OrderRequest) -> Result<max::OrderParams> {
+            let info = self.catalog.market(market)?;
+            let side = order.side;
+            order.volume = info.round_volume(order.volume);
+            order.price = order.price.map(|price| info.round_price(side, price));
+            order.stop_price = order.stop_price.map(|price| info.round_price(side, price));
+
+            let price = order.price.or(order.stop_price);
+            if let Err(message) = info.check_order(order.volume, price) {
+                return Err(Error::InvalidOrder(format!("[{}] {}", market, message)));
             }
 
-            /* prepare payload data */
-            let payload_raw = Payload {
-                nonce: timestamp.to_string(),
-                id: Some(543210),
-                client_oid: Some("max_rs_api_case_create_order".into()),
-                path: api_path.into(),
+            match order.build() {
+                Ok(params) => Ok(params),
+                Err(message) => Err(Error::InvalidOrder(format!("[{}] {}", market, message))),
+            }
+        }
+
+        /*
+         * orders are not idempotent, a failed submission is only sent again
+         * once its client_oid proves the previous attempt did not land
+         */
+        pub async fn submit_order(&self, market: &str, order: OrderRequest) -> Result<u64> {
+            let client_oid = order.client_oid.clone().unwrap_or_else(new_client_oid);
+            let request = max::CreateOrder {
+                market: market.into(),
+                order: self.fit_order(market, order.client_oid(client_oid.clone()))?,
             };
 
-            let params = format!(
-                "nonce={}&id={}&client_oid={}",
-                payload_raw.nonce,
-                self.option_to_string(&payload_raw.id),
-                self.option_to_string(&payload_raw.client_oid),
-            );
-            //println!("params: {}", params);
+            let mut attempt = 0;
+            loop {
+                let err = match self.client.send(&request).await {
+                    Ok(respond) => return Ok(respond.id),
+                    Err(err) => err,
+                };
 
-            /* pack the payload with Base64 format */
-            let payload_json_b64 =
-                b64_encode(serde_json::to_string(&payload_raw).unwrap().as_bytes());
-            //println!("json: {}", serde_json::to_string(&payload_raw).unwrap());
+                if !err.is_retryable() || attempt >= max::MAX_RETRIES {
+                    return Err(err);
+                }
 
-            /* build client embedded with authorization info */
-            let (client, request) = self.build_auth_client(&api_path, &params, &payload_json_b64);
+                match self.find_order(&client_oid).await {
+                    Ok(Some(order)) => return Ok(order.id),
+                    Ok(None) => {}
+                    Err(_) => return Err(err),
+                }
 
-            /* send the request and wait for the respond */
-            let respond = client
-                .post(request)
-                .send()
-                .unwrap()
-                .json::<serde_json::Value>()
-                .unwrap();
-            //println!("result: {:?}", respond);
-
-            if respond["error"] != serde_json::Value::Null {
-                error!(
-                    "Failed to delete the order: {}",
-                    respond["error"]["message"]
+                attempt += 1;
+                warn!(
+                    "[{}] order {} did not land, retry {}: {}",
+                    market, client_oid, attempt, err
                 );
+                tokio::time::sleep(MaxClient::backoff(attempt)).await;
             }
         }
 
-        pub fn get_orders(&mut self) {
-            let api_path = "/api/v2/orders";
-
-            /* get milliseconds time of UNIX epoch time since 1970 */
-            let timestamp = get_timestamp(SystemTime::now());
-
-            #[derive(Serialize)]
-            struct Payload {
-                nonce: String,
-                market: String,
-                state: String,
-                order_by: Option<String>,
-                group_id: Option<u64>,
-                pagination: Option<bool>,
-                page: Option<u64>,
-                limit: Option<u64>,
-                offset: Option<u64>,
-                path: String,
-            }
-
-            /* prepare payload data */
-            let payload_raw = Payload {
-                nonce: timestamp.to_string(),
-                market: "dogetwd".into(),
-                state: "wait".into(),
-                order_by: Some("asc".into()),
-                group_id: None,
-                pagination: Some(true),
-                page: Some(1),
-                limit: Some(100),
-                offset: Some(0),
-                path: api_path.into(),
-            };
+        /*
+         * submit the orders in one request, every order gets its own
+         * result. the batch is retried like submit_order for the orders that did not land.
+         * an Api error is MAX rejecting that very order, a failure of the request
+         * as a whole is reported as BatchFailed, even for a lone order.
+         */
+        pub async fn submit_orders(
+            &self,
+            market: &str,
+            orders: Vec<OrderRequest>,
+        ) -> Vec<Result<u64>> {
+            let mut results: Vec<Option<Result<u64>>> = Vec::new();
+            let mut pending = Vec::new();
+
+            for (i, order) in orders.into_iter().enumerate() {
+                let client_oid = order.client_oid.clone().unwrap_or_else(new_client_oid);
+                match self.fit_order(market, order.client_oid(client_oid)) {
+                    Ok(params) => {
+                        results.push(None);
+                        pending.push((i, params));
+                    }
+                    Err(err) => results.push(Some(Err(err))),
+                }
+            }
 
-            let params = format!(
-                "nonce={}&market={}&state={}&order_by={}&\
-                 group_id={}&pagination={}&page={}&\
-                 limit={}&offset={}",
-                payload_raw.nonce,
-                payload_raw.market,
-                payload_raw.state,
-                self.option_to_string(&payload_raw.order_by),
-                self.option_to_string(&payload_raw.group_id),
-                self.option_to_string(&payload_raw.pagination),
-                self.option_to_string(&payload_raw.page),
-                self.option_to_string(&payload_raw.limit),
-                self.option_to_string(&payload_raw.offset)
-            );
-            //println!("params: {}", params);
+            let mut attempt = 0;
+            while !pending.is_empty() {
+                let respond = self
+                    .client
+                    .send(&max::CreateOrders {
+                        market: market.into(),
+                        orders: pending.iter().map(|(_, params)| params.clone()).collect(),
+                        group_id: None,
+                    })
+                    .await;
+
+                let err = match respond {
+                    Ok(respond) if respond.len() == pending.len() => {
+                        /* hand the exchange results to the orders that were sent */
+                        for ((i, _), order_result) in pending.drain(..).zip(respond) {
+                            results[i] = Some(match (order_result.order, order_result.error) {
+                                (_, Some(err)) => Err(err.into()),
+                                (Some(order), None) => Ok(order.id),
+                                (None, None) => Err(Error::BatchFailed(
+                                    "order result without order or error".into(),
+                                )),
+                            });
+                        }
+                        break;
+                    }
+                    Ok(respond) => Error::BatchFailed(format!(
+                        "{} results for {} orders",
+                        respond.len(),
+                        pending.len()
+                    )),
+                    Err(err) => err,
+                };
 
-            /* pack the payload with Base64 format */
-            let payload_json_b64 =
-                b64_encode(serde_json::to_string(&payload_raw).unwrap().as_bytes());
-            //println!("json: {}", serde_json::to_string(&payload_raw).unwrap());
+                if !err.is_retryable() || attempt >= max::MAX_RETRIES {
+                    for (i, _) in pending.drain(..) {
+                        results[i] = Some(Err(Error::BatchFailed(err.to_string())));
+                    }
+                    break;
+                }
 
-            /* build client embedded with authorization info */
-            let (client, request) = self.build_auth_client(&api_path, &params, &payload_json_b64);
+                /* keep the orders that landed, resend only the missing ones */
+                let mut missing = Vec::new();
+                for (i, params) in pending.drain(..) {
+                    let client_oid = params.client_oid.clone().unwrap_or_default();
+                    match self.find_order(&client_oid).await {
+                        Ok(Some(order)) => results[i] = Some(Ok(order.id)),
+                        Ok(None) => missing.push((i, params)),
+                        Err(_) => results[i] = Some(Err(Error::BatchFailed(err.to_string()))),
+                    }
+                }
+                pending = missing;
+
+                attempt += 1;
+                warn!(
+                    "[{}] {} orders did not land, retry {}: {}",
+                    market,
+                    pending.len(),
+                    attempt,
+                    err
+                );
+                tokio::time::sleep(MaxClient::backoff(attempt)).await;
+            }
 
-            /* send the request and wait for the respond */
-            let vec = client
-                .get(request)
-                .send()
-                .unwrap()
-                .json::<Vec<serde_json::Value>>()
-                .unwrap();
-            //println!("result: {:?}", vec);
+            results.into_iter().map(|result| result.unwrap()).collect()
+        }
 
-            /* read orders */
-            for i in 0..vec.len() {
-                println!(
-                    "[{}] price:{}, remaining_volume:{}",
-                    &vec[i]["market"], &vec[i]["price"], &vec[i]["remaining_volume"]
-                );
+        /* look an order up by client_oid, None if MAX does not know it */
+        async fn find_order(&self, client_oid: &str) -> Result<Option<Order>> {
+            match self
+                .client
+                .send(&max::GetOrder {
+                    id: None,
+                    client_oid: Some(client_oid.into()),
+                })
+                .await
+            {
+                Ok(order) => Ok(Some(order)),
+                /* any other rejection (nonce, auth) says nothing about the order */
+                Err(Error::Api { code, .. }) if code == max::ORDER_NOT_FOUND => Ok(None),
+                Err(err) => Err(err),
             }
         }
 
-        pub fn sync_accounts(&mut self) {
-            let api_path = "/api/v2/members/accounts";
+        pub async fn delete_order(&self, id: u64) -> Result<()> {
+            self.client
+                .send(&max::DeleteOrder {
+                    id: Some(id),
+                    client_oid: None,
+                })
+                .await?;
+
+            Ok(())
+        }
 
-            /* get milliseconds time of UNIX epoch time since 1970 */
-            let timestamp = get_timestamp(SystemTime::now());
+        /* cancel all open orders matching the filters in a single request */
+        pub async fn cancel_orders(
+            &self,
+            market: Option<&str>,
+            side: Option<Side>,
+            group_id: Option<u64>,
+        ) -> Result<Vec<Order>> {
+            let respond = self
+                .client
+                .send(&max::CancelOrders {
+                    market: market.map(|market| market.into()),
+                    side,
+                    group_id,
+                })
+                .await?;
 
-            #[derive(Serialize)]
-            struct Payload {
-                nonce: String,
-                path: String,
-            }
+            Ok(respond)
+        }
 
-            /* prepare payload data */
-            let payload_raw = Payload {
-                nonce: timestamp.to_string(),
-                path: api_path.to_string(),
-            };
+        pub async fn get_order(&self, id: u64) -> Result<Order> {
+            let respond = self
+                .client
+                .send(&max::GetOrder {
+                    id: Some(id),
+                    client_oid: None,
+                })
+                .await?;
 
-            let params = format!("nonce={}", timestamp.to_string());
+            Ok(respond)
+        }
 
-            /* pack the payload with Base64 format */
-            let payload_json_b64 =
-                b64_encode(serde_json::to_string(&payload_raw).unwrap().as_bytes());
+        /* every open order of the market, or of one group of it, across all pages */
+        pub async fn get_orders(&self, market: &str, group_id: Option<u64>) -> Result<Vec<Order>> {
+            let mut pages = self.order_history(market, &[OrderState::Wait], group_id, None, None);
+            let mut orders = Vec::new();
+            while let Some(order) = pages.next().await {
+                orders.push(order?);
+            }
 
-            /* build client embedded with authorization info */
-            let (client, request) = self.build_auth_client(&api_path, &params, &payload_json_b64);
+            Ok(orders)
+        }
 
-            /* send the request and wait for the respond */
-            let vec = client
-                .get(request)
-                .send()
-                .unwrap()
-                .json::<Vec<serde_json::Value>>()
-                .unwrap();
+        /*
+         * orders in the given states created within [since, until), newest first.
+         * MAX cannot filter orders by time, walking back from the newest order
+         * stops at since instead of reading the whole history.
+         */
+        pub fn order_history(
+            &self,
+            market: &str,
+            states: &[OrderState],
+            group_id: Option<u64>,
+            since: Option<DateTime<Utc>>,
+            until: Option<DateTime<Utc>>,
+        ) -> Pages<'_, max::GetOrders> {
+            self.client
+                .pages(max::GetOrders {
+                    market: market.into(),
+                    state: states.to_vec(),
+                    order_by: Some("desc".into()),
+                    group_id,
+                    pagination: None,
+                    page: None,
+                    limit: HISTORY_PAGE_LIMIT,
+                })
+                .within(since, until)
+        }
+
+        /* fills of the account within [since, until), newest first, MAX skips those after until */
+        pub fn trade_history(
+            &self,
+            market: &str,
+            since: Option<DateTime<Utc>>,
+            until: Option<DateTime<Utc>>,
+        ) -> Pages<'_, max::GetMyTrades> {
+            self.client
+                .pages(max::GetMyTrades {
+                    market: market.into(),
+                    /* whole seconds, the fills of the last partial second are trimmed by within */
+                    timestamp: until.map(|until| until.timestamp() + 1),
+                    from: None,
+                    to: None,
+                    order_by: Some("desc".into()),
+                    pagination: None,
+                    page: None,
+                    limit: HISTORY_PAGE_LIMIT,
+                })
+                .within(since, until)
+        }
+
+        pub async fn sync_accounts(&self) -> Result<()> {
+            let vec = self.client.send(&max::GetAccounts {}).await?;
             //println!("result: {:?}", vec);
 
-            /* read accounts */
-            self.accounts.clear();
-            for i in 0..vec.len() {
-                let account = Account {
-                    currency: vec[i]["currency"].to_string().replace("\"", ""),
-                    balance: vec[i]["balance"].to_string(),
-                    locked: vec[i]["locked"].to_string(),
-                    stacked: vec[i]["stacked"].to_string(),
-                    r#type: vec[i]["type"].to_string(),
-                    fiat_currency: vec[i]["fiat_currency"].to_string(),
-                    fiat_balance: vec[i]["balance"].to_string(),
-                };
-                self.accounts.push(account);
+            *self.accounts.lock().unwrap() = vec;
 
+            Ok(())
+        }
+
+        /* the balances of the last sync_accounts */
+        pub fn print_accounts(&self) {
+            for account in self.accounts.lock().unwrap().iter() {
                 println!(
                     "[{}] balance:{}, locked:{}",
-                    &self.accounts[i].currency, &self.accounts[i].balance, &self.accounts[i].locked
+                    &account.currency, &account.balance, &account.locked
                 );
             }
         }
 
-        pub fn get_vip_level(&mut self) {
-            let api_path = "/api/v2/members/vip_level";
+        fn get_balance(&self, currency: &str) -> Decimal {
+            let accounts = self.accounts.lock().unwrap();
+            match accounts.iter().find(|acc| acc.currency == currency) {
+                Some(account) => account.balance,
+                None => Decimal::ZERO,
+            }
+        }
+
+        pub async fn get_vip_level(&self) -> Result<()> {
+            let response = self.client.send(&max::GetVipLevel {}).await?;
+            println!(
+                "maker_fee:{}, taker_fee:{}",
+                response.current_vip_level.maker_fee, response.current_vip_level.taker_fee
+            );
 
-            /* get milliseconds time of UNIX epoch time since 1970 */
-            let timestamp = get_timestamp(SystemTime::now());
+            Ok(())
+        }
 
-            #[derive(Serialize)]
-            struct Payload {
-                nonce: String,
-                path: String,
+        pub async fn run(&mut self) -> Result<()> {
+            let refresh = match self.catalog_time {
+                Some(time) => time.elapsed() >= CATALOG_REFRESH,
+                None => true,
+            };
+            if refresh {
+                /* keep trading on the previous catalog until the refresh succeeds */
+                if let Err(err) = self.sync_catalog().await {
+                    if self.catalog.is_empty() {
+                        return Err(err);
+                    }
+                    warn!("failed to refresh the market catalog: {}", err);
+                }
             }
 
-            /* prepare payload data */
-            let payload_raw = Payload {
-                nonce: timestamp.to_string(),
-                path: api_path.to_string(),
+            /* markets run concurrently, a failing market must not stop the others */
+            let mut grids = std::mem::take(&mut self.grids);
+            let bot = &*self;
+            let rounds = self.markets().into_iter().map(|market| {
+                let grid = grids.remove(&market);
+                async move {
+                    let (grid, result) = bot.run_market(&market, grid).await;
+                    (market, grid, result)
+                }
+            });
+
+            for (market, grid, result) in join_all(rounds).await {
+                if let Some(grid) = grid {
+                    self.grids.insert(market.clone(), grid);
+                }
+                if let Err(err) = result {
+                    error!("[{}] {}", market, err);
+                }
+            }
+
+            Ok(())
+        }
+
+        /* one round of a market, the grid is handed back even if the round fails */
+        async fn run_market(
+            &self,
+            trade_pair: &str,
+            grid: Option<Grid>,
+        ) -> (Option<Grid>, Result<()>) {
+            let market = match self.catalog.market(trade_pair) {
+                Ok(market) => market,
+                Err(err) => return (grid, Err(err)),
+            };
+            let base_currency = market.base_unit.clone();
+            let quote_currency = market.quote_unit.clone();
+
+            let mut grid = match grid {
+                Some(grid) => grid,
+                None => {
+                    info!(
+                        "[{}] start grid trading, base={}, quote={}, group={}",
+                        trade_pair,
+                        base_currency,
+                        quote_currency,
+                        self.strategy_of(trade_pair).group_id
+                    );
+                    /* a grid that placed seed orders is kept even if seeding failed */
+                    match self
+                        .start_grid(trade_pair, &base_currency, &quote_currency)
+                        .await
+                    {
+                        (Some(grid), Ok(())) => grid,
+                        (grid, result) => return (grid, result),
+                    }
+                }
+            };
+
+            /* keep the grid even if this round fails, its levels track live orders */
+            let result = self
+                .update_grid(&mut grid, &base_currency, &quote_currency)
+                .await;
+
+            (Some(grid), result)
+        }
+
+        async fn start_grid(
+            &self,
+            market: &str,
+            base_currency: &str,
+            quote_currency: &str,
+        ) -> (Option<Grid>, Result<()>) {
+            let ticker = match self.get_ticker_info(market).await {
+                Ok(ticker) => ticker,
+                Err(err) => return (None, Err(err)),
+            };
+            let strategy = self.strategy_of(market);
+
+            /* a new grid does not know the orders of a previous run, take them off the book */
+            let stale = match self
+                .cancel_orders(Some(market), None, Some(strategy.group_id))
+                .await
+            {
+                Ok(stale) => stale,
+                Err(err) => return (None, Err(err)),
             };
+            if !stale.is_empty() {
+                warn!(
+                    "[{}] cancelled {} orders left in group {}",
+                    market,
+                    stale.len(),
+                    strategy.group_id
+                );
+            }
 
-            let params = format!("nonce={}", timestamp.to_string());
+            let mut grid = Grid::new(
+                market,
+                strategy.grid_mode,
+                strategy.long,
+                strategy.lower_price,
+                strategy.upper_price,
+                strategy.grid_number.to_u32().unwrap(),
+                strategy.quantity,
+            );
+            grid.layout(ticker.last);
 
-            /* pack the payload with Base64 format */
-            let payload_json_b64 =
-                b64_encode(serde_json::to_string(&payload_raw).unwrap().as_bytes());
+            info!(
+                "[{}] {} {:?} grid range:{}~{}, last price:{}, buy levels:{}, sell levels:{}",
+                grid.symbol,
+                if grid.long { "long" } else { "short" },
+                grid.mode,
+                grid.lower_price,
+                grid.upper_price,
+                ticker.last,
+                grid.buy_levels().count(),
+                grid.sell_levels().count()
+            );
+
+            let result = self
+                .seed_grid(&mut grid, base_currency, quote_currency, ticker.sell)
+                .await;
 
-            /* build client embedded with authorization info */
-            let (client, request) = self.build_auth_client(&api_path, &params, &payload_json_b64);
+            (Some(grid), result)
+        }
 
-            /* send the request and wait for the respond */
-            let response = client
-                .get(request)
-                .send()
+        fn strategy_of(&self, symbol: &str) -> &TradeStrategy {
+            self.trade_strategies
+                .iter()
+                .find(|s| s.enabled && s.symbol == symbol)
                 .unwrap()
-                .json::<serde_json::Value>()
-                .unwrap();
-            println!(
-                "maker_fee:{}, taker_fee:{}",
-                response["current_vip_level"]["maker_fee"],
-                response["current_vip_level"]["taker_fee"]
+        }
+
+        fn risk_control_of(&self, symbol: &str) -> Result<&RiskControl> {
+            match self.risk_control.iter().find(|r| r.symbol == symbol) {
+                Some(risk_ctrl) => Ok(risk_ctrl),
+                None => config_error(format!("[{}] risk control is not found", symbol)),
+            }
+        }
+
+        /*
+         * buy the base inventory required by the sell levels of the grid. a risk
+         * limit stops the grid, other failures are resumed by update_grid.
+         */
+        async fn seed_grid(
+            &self,
+            grid: &mut Grid,
+            base_currency: &str,
+            quote_currency: &str,
+            best_ask: Decimal,
+        ) -> Result<()> {
+            let result = self
+                .seed_missing_base(grid, base_currency, quote_currency, best_ask)
+                .await;
+
+            match &result {
+                Ok(()) => grid.needs_seed = false,
+                Err(Error::Halted(_)) => grid.stopped = true,
+                Err(_) => grid.needs_seed = true,
+            }
+
+            result
+        }
+
+        async fn seed_missing_base(
+            &self,
+            grid: &mut Grid,
+            base_currency: &str,
+            quote_currency: &str,
+            best_ask: Decimal,
+        ) -> Result<()> {
+            let min_base_asset_balance = self.risk_control_of(&grid.symbol)?.min_base_asset_balance;
+
+            self.sync_accounts().await?;
+            let base_balance = self.get_balance(base_currency);
+
+            let required_base: Decimal = grid.sell_levels().map(|level| level.volume).sum();
+            let missing_base = required_base + min_base_asset_balance - base_balance;
+            if missing_base <= Decimal::ZERO {
+                return Ok(());
+            }
+
+            self.buy_inventory(grid, base_currency, quote_currency, missing_base, best_ask)
+                .await
+        }
+
+        /*
+         * buy base inventory for the sell levels of the grid within the risk limits,
+         * the orders are awaited as seed orders before the grid places its own.
+         * the balances must be synced by the caller.
+         */
+        async fn buy_inventory(
+            &self,
+            grid: &mut Grid,
+            base_currency: &str,
+            quote_currency: &str,
+            missing_base: Decimal,
+            best_ask: Decimal,
+        ) -> Result<()> {
+            let risk_ctrl = self.risk_control_of(&grid.symbol)?;
+            let max_base_asset_balance = risk_ctrl.max_base_asset_balance;
+            let min_quote_balance = risk_ctrl.min_quote_balance;
+            let max_order_amount = risk_ctrl.max_order_amount;
+            let group_id = self.strategy_of(&grid.symbol).group_id;
+
+            let base_balance = self.get_balance(base_currency);
+            let quote_balance = self.get_balance(quote_currency);
+
+            /* the fee would leave the highest sell level short */
+            let missing_base = missing_base / (Decimal::ONE - FEE_MARGIN);
+            let price = best_ask * (Decimal::ONE + MARKET_SLIPPAGE);
+            let cost = missing_base * price;
+
+            if base_balance + missing_base > max_base_asset_balance {
+                return Err(Error::Halted(format!(
+                    "[{}] seeding {} {} exceeds max_base_asset_balance {}",
+                    grid.symbol, missing_base, base_currency, max_base_asset_balance
+                )));
+            }
+
+            if quote_balance - cost < min_quote_balance {
+                return Err(Error::Halted(format!(
+                    "[{}] seeding costs {} {} but only {} is available above min_quote_balance",
+                    grid.symbol,
+                    cost,
+                    quote_currency,
+                    quote_balance - min_quote_balance
+                )));
+            }
+
+            let required_quote: Decimal = grid
+                .buy_levels()
+                .map(|level| level.volume * level.price)
+                .sum();
+            if quote_balance - cost - min_quote_balance < required_quote {
+                warn!(
+                    "[{}] {} {} left after seeding cannot fund all buy levels ({})",
+                    grid.symbol,
+                    quote_balance - cost - min_quote_balance,
+                    quote_currency,
+                    required_quote
+                );
+            }
+
+            info!(
+                "[{}] seeding {} {} @ {} before placing the sell levels",
+                grid.symbol, missing_base, base_currency, price
             );
+
+            /* chunks placed before a failure are still awaited as seed orders */
+            let (ids, result) = self
+                .submit_in_chunks(
+                    grid,
+                    "s",
+                    OrderRequest::new(OrderType::Limit, Side::Buy, missing_base)
+                        .price(price)
+                        .group_id(group_id),
+                    max_order_amount,
+                )
+                .await;
+            grid.seed_orders.extend(ids);
+
+            result
         }
 
-        pub fn run(&mut self) {
-            let trade_pair = "dogetwd";
-            let mut base_currency = "".into();
-            let mut quote_currency = "".into();
+        /*
+         * split the order of the grid to respect max_order_amount, the ids of the
+         * chunks that landed are returned along with the failure
+         */
+        async fn submit_in_chunks(
+            &self,
+            grid: &mut Grid,
+            level: &str,
+            order: OrderRequest,
+            max_order_amount: Decimal,
+        ) -> (Vec<u64>, Result<()>) {
+            let group_id = self.strategy_of(&grid.symbol).group_id;
+            let mut orders = Vec::new();
+
+            let mut remaining = order.volume;
+            while remaining > Decimal::ZERO {
+                let chunk = remaining.min(max_order_amount);
+                let mut chunk_order = order.clone();
+                chunk_order.volume = chunk;
+                chunk_order.client_oid = Some(strategy_client_oid(
+                    group_id,
+                    grid.generation,
+                    level,
+                    grid.next_sequence(),
+                ));
+                info!(
+                    "[{}] {} {} @ {} as {}",
+                    grid.symbol,
+                    chunk_order.side,
+                    chunk,
+                    chunk_order.price.unwrap_or_default(),
+                    chunk_order.client_oid.as_deref().unwrap_or_default()
+                );
+                orders.push(chunk_order);
+                remaining -= chunk;
+            }
+
+            let mut ids = Vec::new();
+            let mut failure = None;
+            for batch in orders.chunks(MAX_BATCH_ORDERS) {
+                for result in self.submit_orders(&grid.symbol, batch.to_vec()).await {
+                    match result {
+                        Ok(id) => ids.push(id),
+                        Err(err) => failure = Some(err),
+                    }
+                }
+            }
+
+            match failure {
+                Some(err) => (ids, Err(err)),
+                None => (ids, Ok(())),
+            }
+        }
+
+        /* cancel every order of the grid at once and return the base inventory it holds */
+        async fn cancel_grid_orders(&self, grid: &mut Grid) -> Result<Decimal> {
+            let group_id = self.strategy_of(&grid.symbol).group_id;
+            let cancelled = self
+                .cancel_orders(Some(&grid.symbol), None, Some(group_id))
+                .await?;
+            info!("[{}] cancelled {} orders", grid.symbol, cancelled.len());
+
+            grid.seed_orders.clear();
+
+            let strategy = self.strategy_of(&grid.symbol);
+            let cancelled: HashMap<u64, Order> = cancelled
+                .into_iter()
+                .map(|order| (order.id, order))
+                .collect();
+
+            let mut base_held = Decimal::ZERO;
+            for level in grid.levels.iter_mut() {
+                /* fills since the last poll count too, orders that filled meanwhile are read */
+                if let (true, Some(id)) = (level.is_pending(), level.order_id()) {
+                    let order = match cancelled.get(&id) {
+                        Some(order) => Ok(order.clone()),
+                        None => self.get_order(id).await,
+                    };
+
+                    match order {
+                        Ok(order) => level.on_order_update(
+                            &grid.symbol,
+                            order.state,
+                            order.executed_volume,
+                            strategy.profit_spread,
+                            grid.long,
+                        ),
+                        Err(err) => {
+                            warn!(
+                                "[{}] level {}: unable to read order {}: {}",
+                                grid.symbol, level.index, id, err
+                            );
+                        }
+                    }
+                }
+
+                if level.is_pending() {
+                    level.on_cancelled(&grid.symbol);
+                }
 
-            let mut cnt: u32 = 0;
-            let mut first_currency = "".into();
-            let mut second_currency = "".into();
+                base_held += level.base_held();
+            }
+
+            Ok(base_held)
+        }
 
-            for i in 0..self.accounts.len() {
-                let currency = &self.accounts[i].currency;
+        /* stop every running grid and take its orders off the book */
+        pub async fn shutdown(&mut self) -> Result<()> {
+            let mut grids = std::mem::take(&mut self.grids);
+            let mut result = Ok(());
+
+            for grid in grids.values_mut().filter(|grid| !grid.stopped) {
+                match self.cancel_grid_orders(grid).await {
+                    Ok(_) => grid.stopped = true,
+                    Err(err) => {
+                        error!("[{}] failed to cancel the grid: {}", grid.symbol, err);
+                        result = Err(err);
+                    }
+                }
+            }
+
+            self.grids = grids;
+            result
+        }
 
-                if trade_pair.contains(currency) {
-                    if cnt == 0 {
-                        first_currency = currency.clone();
-                    } else if cnt == 1 {
-                        second_currency = currency.clone();
+        async fn handle_breakout(
+            &self,
+            grid: &mut Grid,
+            ticker: &Ticker,
+            base_currency: &str,
+            quote_currency: &str,
+        ) -> Result<()> {
+            let breakout_policy = self.strategy_of(&grid.symbol).breakout_policy;
+
+            match breakout_policy {
+                BreakoutPolicy::Keep => {}
+                BreakoutPolicy::Pause => {
+                    if !grid.paused {
+                        warn!(
+                            "[{}] price {} left the grid range, pausing",
+                            grid.symbol, ticker.last
+                        );
+                        grid.paused = true;
+                    }
+                }
+                BreakoutPolicy::Cancel => {
+                    warn!(
+                        "[{}] price {} left the grid range, cancelling the grid",
+                        grid.symbol, ticker.last
+                    );
+                    self.cancel_grid_orders(grid).await?;
+                    grid.stopped = true;
+                }
+                BreakoutPolicy::Close => {
+                    warn!(
+                        "[{}] price {} left the grid range, closing the position",
+                        grid.symbol, ticker.last
+                    );
+                    let base_held = self.cancel_grid_orders(grid).await?;
+                    grid.stopped = true;
+
+                    if base_held > Decimal::ZERO {
+                        let max_order_amount = self.risk_control_of(&grid.symbol)?.max_order_amount;
+                        let group_id = self.strategy_of(&grid.symbol).group_id;
+                        let price = ticker.buy * (Decimal::ONE - MARKET_SLIPPAGE);
+
+                        info!(
+                            "[{}] selling {} {} @ {}",
+                            grid.symbol, base_held, base_currency, price
+                        );
+                        let (_, result) = self
+                            .submit_in_chunks(
+                                grid,
+                                "c",
+                                OrderRequest::new(OrderType::Limit, Side::Sell, base_held)
+                                    .price(price)
+                                    .group_id(group_id),
+                                max_order_amount,
+                            )
+                            .await;
+                        if let Err(err) = result {
+                            error!("[{}] failed to close the position: {}", grid.symbol, err);
+                        }
+                    }
+                }
+                BreakoutPolicy::Recenter => {
+                    self.cancel_grid_orders(grid).await?;
+
+                    if !grid.recenter(ticker.last, self.strategy_of(&grid.symbol).profit_spread) {
+                        error!(
+                            "[{}] unable to recenter the grid around {}, stopping",
+                            grid.symbol, ticker.last
+                        );
+                        grid.stopped = true;
+                        return Ok(());
                     }
 
-                    cnt = cnt + 1;
+                    info!(
+                        "[{}] grid recentered to {}~{}",
+                        grid.symbol, grid.lower_price, grid.upper_price
+                    );
+                    self.seed_grid(grid, base_currency, quote_currency, ticker.sell)
+                        .await?;
+                }
+                BreakoutPolicy::Trail => {
+                    self.trail_grid(grid, ticker, base_currency, quote_currency)
+                        .await?
                 }
             }
 
-            let test_pair1 = format!("{}{}", first_currency, second_currency);
-            let test_pair2 = format!("{}{}", second_currency, first_currency);
+            Ok(())
+        }
 
-            if trade_pair == test_pair1 {
-                base_currency = first_currency;
-                quote_currency = second_currency;
-            } else if trade_pair == test_pair2 {
-                base_currency = second_currency;
-                quote_currency = first_currency;
+        /* shift the window one level at a time until it covers the price again */
+        async fn trail_grid(
+            &self,
+            grid: &mut Grid,
+            ticker: &Ticker,
+            base_currency: &str,
+            quote_currency: &str,
+        ) -> Result<()> {
+            let last_price = ticker.last;
+            let direction = if last_price > grid.upper_price {
+                ShiftDirection::Up
             } else {
-                error!("unknown currency pair!");
+                ShiftDirection::Down
+            };
+
+            let profit_spread = self.strategy_of(&grid.symbol).profit_spread;
+            let mut added_sells = Vec::new();
+            let mut removed_base = Decimal::ZERO;
+            while !grid.in_range(last_price) {
+                let removed = match grid.shift(direction, last_price, profit_spread) {
+                    Some(removed) => removed,
+                    None => {
+                        warn!(
+                            "[{}] grid cannot trail below {}",
+                            grid.symbol, grid.lower_price
+                        );
+                        break;
+                    }
+                };
+
+                /* a sell level added by the shift holds nothing yet, its base is bought first */
+                let added = match direction {
+                    ShiftDirection::Up => grid.levels.last().unwrap(),
+                    ShiftDirection::Down => grid.levels.first().unwrap(),
+                };
+                if added.active && added.side == Side::Sell {
+                    added_sells.push(added.price);
+                }
+
+                /* what the removed level holds now backs the new sell levels */
+                removed_base += removed.base_held();
+                if let (true, Some(id)) = (removed.is_pending(), removed.order_id()) {
+                    if let Err(err) = self.delete_order(id).await {
+                        error!("[{}] failed to delete order {}: {}", grid.symbol, id, err);
+                    }
+                }
+
+                let shift = grid.shifts.last().unwrap();
+                info!(
+                    "[{}] grid shifted {:?} to {}~{} ({} shifts so far)",
+                    grid.symbol,
+                    shift.direction,
+                    shift.lower_price,
+                    shift.upper_price,
+                    grid.shifts.len()
+                );
             }
 
-            info!(
-                "simulate trading, base={}, quote={}",
-                base_currency, quote_currency
+            if added_sells.is_empty() {
+                return Ok(());
+            }
+
+            let volume: Decimal = grid
+                .sell_levels()
+                .filter(|level| added_sells.contains(&level.price))
+                .map(|level| level.volume)
+                .sum();
+            let volume = volume - removed_base;
+            if volume <= Decimal::ZERO {
+                return Ok(());
+            }
+
+            self.sync_accounts().await?;
+            let result = self
+                .buy_inventory(grid, base_currency, quote_currency, volume, ticker.sell)
+                .await;
+
+            /* without the inventory the new sell levels stay idle */
+            if result.is_err() {
+                for level in grid.levels.iter_mut() {
+                    if added_sells.contains(&level.price) {
+                        level.active = false;
+                    }
+                }
+            }
+
+            result
+        }
+
+        /* returns true once every seed order is filled */
+        async fn check_seed_orders(&self, grid: &mut Grid) -> Result<bool> {
+            let mut pending = Vec::new();
+
+            for id in grid.seed_orders.clone() {
+                let order = match self.get_order(id).await {
+                    Ok(order) => order,
+                    Err(err) => {
+                        warn!(
+                            "[{}] unable to read seed order {}: {}",
+                            grid.symbol, id, err
+                        );
+                        pending.push(id);
+                        continue;
+                    }
+                };
+
+                match order.state {
+                    OrderState::Done => {
+                        info!(
+                            "[{}] seed order {} filled {}",
+                            grid.symbol, id, order.executed_volume
+                        );
+                    }
+                    OrderState::Cancel => {
+                        grid.stopped = true;
+                        return Err(Error::Halted(format!(
+                            "[{}] seed order {} is cancelled",
+                            grid.symbol, id
+                        )));
+                    }
+                    _ => pending.push(id),
+                }
+            }
+
+            grid.seed_orders = pending;
+            Ok(grid.seed_orders.is_empty())
+        }
+
+        async fn update_grid(
+            &self,
+            grid: &mut Grid,
+            base_currency: &str,
+            quote_currency: &str,
+        ) -> Result<()> {
+            if grid.stopped {
+                return Ok(());
+            }
+
+            let ticker = self.get_ticker_info(&grid.symbol).await?;
+            if !grid.in_range(ticker.last) {
+                self.handle_breakout(grid, &ticker, base_currency, quote_currency)
+                    .await?;
+            } else if grid.paused {
+                info!(
+                    "[{}] price {} is back in the grid range, resuming",
+                    grid.symbol, ticker.last
+                );
+                grid.paused = false;
+            }
+
+            if grid.stopped {
+                return Ok(());
+            }
+
+            /* lay out the grid only after the seed inventory arrives */
+            if !grid.seed_orders.is_empty() && !self.check_seed_orders(grid).await? {
+                return Ok(());
+            }
+
+            /* the balance tells what a seeding that failed part way still misses */
+            if grid.needs_seed {
+                self.seed_grid(grid, base_currency, quote_currency, ticker.sell)
+                    .await?;
+                if !grid.seed_orders.is_empty() {
+                    return Ok(());
+                }
+            }
+
+            let profit_spread = self.strategy_of(&grid.symbol).profit_spread;
+            let long = grid.long;
+            let paused = grid.paused;
+
+            /* only the orders of the strategy, manual orders on the market are left alone */
+            let group_id = self.strategy_of(&grid.symbol).group_id;
+            let open_orders: HashMap<u64, Order> = self
+                .get_orders(&grid.symbol, Some(group_id))
+                .await?
+                .into_iter()
+                .map(|order| (order.id, order))
+                .collect();
+
+            let mut batch = Vec::new();
+
+            for (i, level) in grid.levels.iter_mut().enumerate() {
+                if !level.active {
+                    continue;
+                }
+
+                /* feed the exchange view of the resting order into the level */
+                if let (true, Some(id)) = (level.is_pending(), level.order_id()) {
+                    let order = match open_orders.get(&id) {
+                        Some(order) => Ok(order.clone()),
+                        None => self.get_order(id).await,
+                    };
+
+                    /* keep the level pending until the order can be read again */
+                    match order {
+                        Ok(order) => level.on_order_update(
+                            &grid.symbol,
+                            order.state,
+                            order.executed_volume,
+                            profit_spread,
+                            long,
+                        ),
+                        Err(err) => {
+                            warn!(
+                                "[{}] level {}: unable to read order {}: {}",
+                                grid.symbol, level.index, id, err
+                            );
+                        }
+                    }
+                }
+
+                /* hold new orders until the price returns to the range */
+                if paused {
+                    continue;
+                }
+
+                let order = match level.next_order(profit_spread) {
+                    Some(order) => order,
+                    None => continue,
+                };
+
+                if order.price <= Decimal::ZERO {
+                    error!(
+                        "[{}] level {}: invalid order price {}",
+                        grid.symbol, level.index, order.price
+                    );
+                    continue;
+                }
+
+                batch.push((i, order));
+            }
+
+            self.place_level_orders(grid, batch).await;
+
+            Ok(())
+        }
+
+        /* submit the orders of many levels with as few requests as possible */
+        async fn place_level_orders(&self, grid: &mut Grid, batch: Vec<(usize, LevelOrder)>) {
+            /* post-only keeps the grid on maker fees, MAX rejects orders that would take */
+            let strategy = self.strategy_of(&grid.symbol);
+            let ord_type = match strategy.post_only {
+                true => OrderType::PostOnly,
+                false => OrderType::Limit,
+            };
+
+            let mut pending = Vec::new();
+            for (i, mut order) in batch {
+                let index = grid.levels[i].index;
+
+                match order.client_oid.clone() {
+                    /* a failed submission may have landed after all, never place it twice */
+                    Some(client_oid) => match self.find_order(&client_oid).await {
+                        Ok(Some(placed)) => {
+                            info!(
+                                "[{}] level {}: order {} landed as {}",
+                                grid.symbol, index, client_oid, placed.id
+                            );
+                            grid.levels[i].on_submitted(&grid.symbol, order, placed.id);
+                            continue;
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            warn!(
+                                "[{}] level {}: unable to look up order {}: {}",
+                                grid.symbol, index, client_oid, err
+                            );
+                            continue;
+                        }
+                    },
+                    None => {
+                        order.client_oid = Some(strategy_client_oid(
+                            strategy.group_id,
+                            grid.generation,
+                            &index.to_string(),
+                            grid.next_sequence(),
+                        ));
+                    }
+                }
+
+                info!(
+                    "[{}] level {}: {} {} @ {} as {}",
+                    grid.symbol,
+                    index,
+                    order.side,
+                    order.volume,
+                    order.price,
+                    order.client_oid.as_deref().unwrap_or_default()
+                );
+                pending.push((i, order));
+            }
+
+            for chunk in pending.chunks(MAX_BATCH_ORDERS) {
+                let orders = chunk
+                    .iter()
+                    .map(|(_, order)| {
+                        let mut request = OrderRequest::new(ord_type, order.side, order.volume)
+                            .price(order.price)
+                            .group_id(strategy.group_id);
+                        request.client_oid = order.client_oid.clone();
+                        request
+                    })
+                    .collect();
+
+                let results = self.submit_orders(&grid.symbol, orders).await;
+
+                for ((i, order), result) in chunk.iter().zip(results) {
+                    let level = &mut grid.levels[*i];
+                    match result {
+                        Ok(id) => level.on_submitted(&grid.symbol, order.clone(), id),
+                        Err(err) => {
+                            error!(
+                                "[{}] level {}: {} failed: {}",
+                                grid.symbol,
+                                level.index,
+                                order.client_oid.as_deref().unwrap_or_default(),
+                                err
+                            );
+                            /*
+                             * only an order refused locally or by MAX itself is final, any
+                             * other failure may have landed and is looked up by its client_oid
+                             */
+                            if matches!(err, Error::InvalidOrder(_) | Error::Api { .. }) {
+                                level.on_submit_rejected(
+                                    &grid.symbol,
+                                    order.clone(),
+                                    strategy.profit_spread,
+                                    grid.long,
+                                );
+                            } else {
+                                level.on_submit_failed(&grid.symbol, order.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /* 2100-01-01, client_oids keep their length until the generation outgrows 8 hex digits */
+        const LATE_GENERATION: i64 = 4_102_444_800;
+
+        #[test]
+        fn strategy_client_oid_format() {
+            assert_eq!(
+                strategy_client_oid(0x369dffcc, 0x6500_0000, "12", 0x1f),
+                "ccgt-369dffcc-65000000-12-1f"
             );
+            assert_eq!(
+                strategy_client_oid(7, 0x6500_0000, "s", 1),
+                "ccgt-7-65000000-s-1"
+            );
+        }
+
+        #[test]
+        fn strategy_client_oid_fits_at_the_largest_ids() {
+            let client_oid =
+                strategy_client_oid(u32::MAX as u64, LATE_GENERATION, "9999", u32::MAX);
+            assert_eq!(client_oid.len(), MAX_CLIENT_OID_LEN);
+
+            let client_oid =
+                strategy_client_oid(u32::MAX as u64, LATE_GENERATION, "10000", u32::MAX);
+            assert!(client_oid.len() > MAX_CLIENT_OID_LEN);
+        }
+
+        #[test]
+        fn config_decimal_reads_integers_and_reals() {
+            let yaml = &YamlLoader::load_from_str(
+                "grid_number: 10\nprofit_spread: 0.5\nquantity: -2\nsymbol: btcusdt",
+            )
+            .unwrap()[0];
+
+            assert_eq!(config_decimal(yaml, "grid_number").unwrap(), dec!(10));
+            assert_eq!(config_decimal(yaml, "profit_spread").unwrap(), dec!(0.5));
+            assert_eq!(config_decimal(yaml, "quantity").unwrap(), dec!(-2));
+            assert!(config_decimal(yaml, "symbol").is_err());
+            assert!(config_decimal(yaml, "upper_price").is_err());
+        }
+
+        #[test]
+        fn default_group_id_is_stable() {
+            assert_eq!(default_group_id("btcusdt"), 0x369dffcc);
+            assert_eq!(default_group_id("ethusdt"), 0x67fef150);
+            assert_eq!(default_group_id("btcusdt"), default_group_id("btcusdt"));
+            assert!(default_group_id("btcusdt") <= u32::MAX as u64);
         }
     }
 }
 
+use log::error;
 use signal_hook::consts::TERM_SIGNALS;
 use signal_hook::flag;
-use std::io::Error;
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread;
 use std::time;
+use tokio::time::MissedTickBehavior;
+
+/* rounds start this often, a slow round delays the next one instead of adding to it */
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/* connect to MAX and print the account overview */
+async fn start_bot() -> error::Result<ccgt::GridTradeBot> {
+    let mut trade_bot = ccgt::GridTradeBot::new()?;
+
+    trade_bot.load_yaml()?;
+    trade_bot.sync_catalog().await?;
+    trade_bot.check_markets()?;
+    trade_bot.sync_accounts().await?;
+    trade_bot.print_accounts();
+    for market in trade_bot.markets() {
+        let group_id = trade_bot.group_id_of(&market);
+        for order in trade_bot.get_orders(&market, Some(group_id)).await? {
+            println!(
+                "[{}] price:{}, remaining_volume:{}",
+                &order.market,
+                order.price.unwrap_or_default(),
+                &order.remaining_volume
+            );
+        }
+    }
+    trade_bot.get_vip_level().await?;
+    trade_bot.get_server_time().await?;
+
+    Ok(trade_bot)
+}
 
-fn main() -> Result<(), Error> {
-    let mut trade_bot = ccgt::GridTradeBot::new();
+#[tokio::main]
+async fn main() -> ExitCode {
+    /* any argument selects an operator action instead of trading */
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        return match cli::run(command, &args[1..]).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
 
-    trade_bot.load_yaml();
-    trade_bot.sync_accounts();
-    trade_bot.get_orders();
-    trade_bot.get_vip_level();
-    trade_bot.get_server_time();
-    trade_bot.get_ticker_info("dogetwd");
-    trade_bot.submit_order();
-    trade_bot.delete_order();
+    let mut trade_bot = match start_bot().await {
+        Ok(trade_bot) => trade_bot,
+        Err(err) => {
+            error!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
     /* configure signal catching */
     let term = Arc::new(AtomicBool::new(false));
     for sig in TERM_SIGNALS {
-        flag::register_conditional_shutdown(*sig, 1, Arc::clone(&term))?;
-        flag::register(*sig, Arc::clone(&term))?;
+        let registered = flag::register_conditional_shutdown(*sig, 1, Arc::clone(&term))
+            .and_then(|_| flag::register(*sig, Arc::clone(&term)));
+        if let Err(err) = registered {
+            error!("unable to catch signal {}: {}", sig, err);
+            return ExitCode::FAILURE;
+        }
     }
 
     /* run trading strategy until stop signal is catched */
-    while !term.load(Ordering::Relaxed) {
-        trade_bot.run();
-        thread::sleep(time::Duration::from_secs(1));
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        poll.tick().await;
+        if term.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Err(err) = trade_bot.run().await {
+            error!("{}", err);
+        }
+    }
+
+    /* leave no grid orders behind once the bot stops */
+    if let Err(err) = trade_bot.shutdown().await {
+        error!("{}", err);
     }
 
     println!("trading is terminated");
 
-    Ok(())
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,169 @@
+use crate::error::{self, Error};
+use crate::max::Side;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Market {
+    pub id: String,
+    pub name: String,
+    pub base_unit: String,
+    pub base_unit_precision: u32,
+    pub min_base_amount: Decimal,
+    pub quote_unit: String,
+    pub quote_unit_precision: u32,
+    pub min_quote_amount: Decimal,
+}
+
+impl Market {
+    /* buy prices are rounded down and sell prices up so the grid never crosses its level */
+    pub fn round_price(&self, side: Side, price: Decimal) -> Decimal {
+        let strategy = match side {
+            Side::Buy => RoundingStrategy::ToZero,
+            Side::Sell => RoundingStrategy::AwayFromZero,
+        };
+
+        price.round_dp_with_strategy(self.quote_unit_precision, strategy)
+    }
+
+    pub fn round_volume(&self, volume: Decimal) -> Decimal {
+        volume.round_dp_with_strategy(self.base_unit_precision, RoundingStrategy::ToZero)
+    }
+
+    /* without a price (market orders) only the base minimum can be checked */
+    pub fn check_order(&self, volume: Decimal, price: Option<Decimal>) -> Result<(), String> {
+        if volume < self.min_base_amount {
+            return Err(format!(
+                "volume {} is below the minimum {} {}",
+                volume, self.min_base_amount, self.base_unit
+            ));
+        }
+
+        if let Some(price) = price {
+            if volume * price < self.min_quote_amount {
+                return Err(format!(
+                    "amount {} is below the minimum {} {}",
+                    volume * price,
+                    self.min_quote_amount,
+                    self.quote_unit
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Currency {
+    pub id: String,
+    pub precision: u32,
+}
+
+/* markets and currencies listed on MAX, keyed by their ids */
+#[derive(Debug, Default)]
+pub struct Catalog {
+    markets: HashMap<String, Market>,
+    currencies: HashMap<String, Currency>,
+}
+
+impl Catalog {
+    pub fn new(markets: Vec<Market>, currencies: Vec<Currency>) -> Catalog {
+        Catalog {
+            markets: markets
+                .into_iter()
+                .map(|market| (market.id.clone(), market))
+                .collect(),
+            currencies: currencies
+                .into_iter()
+                .map(|currency| (currency.id.clone(), currency))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markets.is_empty()
+    }
+
+    /* a market is tradable only if both of its units are listed currencies */
+    pub fn market(&self, id: &str) -> error::Result<&Market> {
+        let market = match self.markets.get(id) {
+            Some(market) => market,
+            None => return Err(Error::Config(format!("[{}] unknown market", id))),
+        };
+
+        for unit in [&market.base_unit, &market.quote_unit] {
+            if !self.currencies.contains_key(unit) {
+                return Err(Error::Config(format!("[{}] unknown currency {}", id, unit)));
+            }
+        }
+
+        Ok(market)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn btcusdt() -> Market {
+        Market {
+            id: "btcusdt".to_string(),
+            name: "BTC/USDT".to_string(),
+            base_unit: "btc".to_string(),
+            base_unit_precision: 6,
+            min_base_amount: dec!(0.0001),
+            quote_unit: "usdt".to_string(),
+            quote_unit_precision: 2,
+            min_quote_amount: dec!(8),
+        }
+    }
+
+    #[test]
+    fn round_price_stays_on_the_level_side() {
+        let market = btcusdt();
+
+        assert_eq!(
+            market.round_price(Side::Buy, dec!(27123.456)),
+            dec!(27123.45)
+        );
+        assert_eq!(
+            market.round_price(Side::Sell, dec!(27123.451)),
+            dec!(27123.46)
+        );
+        assert_eq!(
+            market.round_price(Side::Buy, dec!(27123.45)),
+            dec!(27123.45)
+        );
+        assert_eq!(
+            market.round_price(Side::Sell, dec!(27123.45)),
+            dec!(27123.45)
+        );
+    }
+
+    #[test]
+    fn round_volume_truncates_to_base_precision() {
+        let market = btcusdt();
+
+        assert_eq!(market.round_volume(dec!(0.1234569)), dec!(0.123456));
+        assert_eq!(market.round_volume(dec!(0.0000009)), Decimal::ZERO);
+    }
+
+    #[test]
+    fn check_order_enforces_minimum_amounts() {
+        let market = btcusdt();
+
+        assert!(market.check_order(dec!(0.001), Some(dec!(27000))).is_ok());
+        assert!(market.check_order(dec!(0.0001), None).is_ok());
+        assert!(market.check_order(dec!(0.00009), None).is_err());
+        assert!(market
+            .check_order(dec!(0.00009), Some(dec!(270000)))
+            .is_err());
+        assert!(market.check_order(dec!(0.0002), Some(dec!(27000))).is_err());
+        assert!(market.check_order(dec!(0.0002), Some(dec!(40000))).is_ok());
+    }
+}
@@ -0,0 +1,340 @@
+use super::{
+    Account, Candle, Depth, Order, OrderResult, OrderState, OrderType, Paged, Request, Side,
+    Ticker, Trade, VipLevel,
+};
+use crate::market::{Currency, Market};
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/* requests per second allowed on the endpoints that place or cancel orders */
+const ORDER_RATE_LIMIT: u32 = 5;
+
+/* MAX error code of GetOrder when no order matches the id or client_oid */
+pub const ORDER_NOT_FOUND: i64 = 2004;
+
+/* public endpoints */
+
+#[derive(Serialize)]
+pub struct GetTimestamp {}
+
+impl Request for GetTimestamp {
+    type Response = i64;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/timestamp";
+    const SIGNED: bool = false;
+}
+
+#[derive(Serialize)]
+pub struct GetTicker {
+    #[serde(skip)]
+    pub market: String,
+}
+
+impl Request for GetTicker {
+    type Response = Ticker;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/tickers";
+    const SIGNED: bool = false;
+
+    fn path(&self) -> String {
+        format!("{}/{}", Self::PATH, self.market)
+    }
+}
+
+#[derive(Serialize)]
+pub struct GetMarkets {}
+
+impl Request for GetMarkets {
+    type Response = Vec<Market>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/markets";
+    const SIGNED: bool = false;
+}
+
+#[derive(Serialize)]
+pub struct GetCurrencies {}
+
+impl Request for GetCurrencies {
+    type Response = Vec<Currency>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/currencies";
+    const SIGNED: bool = false;
+}
+
+/* order book snapshot, limit is the number of levels per side */
+#[derive(Serialize)]
+pub struct GetDepth {
+    pub market: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+impl Request for GetDepth {
+    type Response = Depth;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/depth";
+    const SIGNED: bool = false;
+}
+
+/* candles of period minutes, timestamp (seconds) is the start of the first candle */
+#[derive(Serialize)]
+pub struct GetCandles {
+    pub market: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+}
+
+impl Request for GetCandles {
+    type Response = Vec<Candle>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/k";
+    const SIGNED: bool = false;
+}
+
+/* recent trades of the market from every participant */
+#[derive(Serialize)]
+pub struct GetTrades {
+    pub market: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+impl Request for GetTrades {
+    type Response = Vec<Trade>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/trades";
+    const SIGNED: bool = false;
+}
+
+/* private endpoints */
+
+#[derive(Serialize)]
+pub struct CreateOrder {
+    pub market: String,
+    #[serde(flatten)]
+    pub order: OrderParams,
+}
+
+impl Request for CreateOrder {
+    type Response = Order;
+
+    const METHOD: Method = Method::POST;
+    const PATH: &'static str = "/api/v2/orders";
+    const IDEMPOTENT: bool = false;
+    const RATE_LIMIT: u32 = ORDER_RATE_LIMIT;
+}
+
+/* one order of CreateOrder or a CreateOrders batch, built by OrderRequest */
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderParams {
+    pub side: Side,
+    pub volume: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_oid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<Decimal>,
+    pub ord_type: OrderType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct CreateOrders {
+    pub market: String,
+    pub orders: Vec<OrderParams>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u64>,
+}
+
+/* results come back in the order of the submitted orders */
+impl Request for CreateOrders {
+    type Response = Vec<OrderResult>;
+
+    const METHOD: Method = Method::POST;
+    const PATH: &'static str = "/api/v2/orders/multi";
+    const IDEMPOTENT: bool = false;
+    const RATE_LIMIT: u32 = ORDER_RATE_LIMIT;
+}
+
+#[derive(Serialize)]
+pub struct DeleteOrder {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_oid: Option<String>,
+}
+
+impl Request for DeleteOrder {
+    type Response = Order;
+
+    const METHOD: Method = Method::POST;
+    const PATH: &'static str = "/api/v2/order/delete";
+    const RATE_LIMIT: u32 = ORDER_RATE_LIMIT;
+}
+
+/* cancel every open order matching the filters, no filter cancels them all */
+#[derive(Serialize)]
+pub struct CancelOrders {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u64>,
+}
+
+impl Request for CancelOrders {
+    type Response = Vec<Order>;
+
+    const METHOD: Method = Method::POST;
+    const PATH: &'static str = "/api/v2/orders/clear";
+    const RATE_LIMIT: u32 = ORDER_RATE_LIMIT;
+}
+
+#[derive(Serialize)]
+pub struct GetOrder {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_oid: Option<String>,
+}
+
+impl Request for GetOrder {
+    type Response = Order;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/order";
+}
+
+#[derive(Serialize)]
+pub struct GetOrders {
+    pub market: String,
+    pub state: Vec<OrderState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    pub limit: u64,
+}
+
+impl Request for GetOrders {
+    type Response = Vec<Order>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/orders";
+}
+
+impl Paged for GetOrders {
+    type Item = Order;
+
+    fn set_page(&mut self, page: u64) {
+        self.pagination = Some(true);
+        self.page = Some(page);
+    }
+
+    fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    fn created_at(order: &Order) -> DateTime<Utc> {
+        order.created_at
+    }
+
+    fn descending(&self) -> bool {
+        self.order_by.as_deref() == Some("desc")
+    }
+}
+
+/* fills of the account, timestamp (seconds) only returns trades executed before it */
+#[derive(Serialize)]
+pub struct GetMyTrades {
+    pub market: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    pub limit: u64,
+}
+
+impl Request for GetMyTrades {
+    type Response = Vec<Trade>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/trades/my";
+}
+
+impl Paged for GetMyTrades {
+    type Item = Trade;
+
+    fn set_page(&mut self, page: u64) {
+        self.pagination = Some(true);
+        self.page = Some(page);
+    }
+
+    fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    fn created_at(trade: &Trade) -> DateTime<Utc> {
+        trade.created_at
+    }
+
+    fn descending(&self) -> bool {
+        self.order_by.as_deref() == Some("desc")
+    }
+}
+
+#[derive(Serialize)]
+pub struct GetAccounts {}
+
+impl Request for GetAccounts {
+    type Response = Vec<Account>;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/members/accounts";
+}
+
+#[derive(Serialize)]
+pub struct GetVipLevel {}
+
+impl Request for GetVipLevel {
+    type Response = VipLevel;
+
+    const METHOD: Method = Method::GET;
+    const PATH: &'static str = "/api/v2/members/vip_level";
+}
@@ -0,0 +1,346 @@
+use super::clock::Clock;
+use super::governor::Governor;
+use super::pager::{Paged, Pages};
+use super::GetTimestamp;
+use crate::error::{Error, Result};
+use base64::encode as b64_encode;
+use hmac::{Hmac, Mac, NewMac};
+use log::warn;
+use rand::Rng;
+use reqwest::{header, Client, Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime};
+
+const MAX_REST_URL: &str = "https://max-api.maicoin.com";
+const MAX_WS_URL: &str = "wss://max-stream.maicoin.com/ws";
+
+/* retries of a failed idempotent request, with exponential backoff from RETRY_DELAY */
+pub const MAX_RETRIES: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+
+/* a stalled request fails as a timeout and is retried instead of blocking its market */
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/* MAX rejects nonces too far from its own clock, warn well before that */
+const CLOCK_SKEW_WARNING_MS: i64 = 5000;
+
+/*
+ * a MAX endpoint. the implementing struct holds the request parameters,
+ * both the query string and the signed payload are derived from it.
+ */
+pub trait Request: Serialize {
+    type Response: DeserializeOwned;
+
+    const METHOD: Method;
+    const PATH: &'static str;
+    const SIGNED: bool = true;
+    /* false if sending the request twice may have a different effect than once */
+    const IDEMPOTENT: bool = true;
+    /* requests per second the client allows on the endpoint */
+    const RATE_LIMIT: u32 = 10;
+
+    fn path(&self) -> String {
+        Self::PATH.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct Query<'a, R> {
+    nonce: u64,
+    #[serde(flatten)]
+    params: &'a R,
+}
+
+#[derive(Serialize)]
+struct Payload<'a, R> {
+    nonce: u64,
+    path: &'a str,
+    #[serde(flatten)]
+    params: &'a R,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+/* base urls of the exchange, pointed elsewhere for a mock exchange or a proxy */
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    pub rest_url: String,
+    pub ws_url: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Endpoints {
+        Endpoints {
+            rest_url: MAX_REST_URL.into(),
+            ws_url: MAX_WS_URL.into(),
+        }
+    }
+}
+
+pub struct MaxClient {
+    endpoints: Endpoints,
+    access_key: String,
+    secret_key: String,
+    client: Client,
+    governor: Governor,
+    clock: Clock,
+}
+
+/* flatten the parameters into query pairs, arrays become repeated key[] entries */
+fn query_pairs<T: Serialize>(params: &T) -> Result<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+
+    if let Value::Object(map) = serde_json::to_value(params)? {
+        for (key, value) in map {
+            match value {
+                Value::Array(values) => {
+                    for value in values {
+                        pairs.push((format!("{}[]", key), query_value(value)));
+                    }
+                }
+                Value::Null => {}
+                value => pairs.push((key, query_value(value))),
+            }
+        }
+    }
+
+    Ok(pairs)
+}
+
+fn query_value(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        value => value.to_string(),
+    }
+}
+
+fn http_client(timeout: Duration, connect_timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .build()
+        .unwrap()
+}
+
+impl MaxClient {
+    pub fn new(endpoints: Endpoints, access_key: String, secret_key: String) -> MaxClient {
+        MaxClient {
+            endpoints,
+            access_key,
+            secret_key,
+            client: http_client(REQUEST_TIMEOUT, CONNECT_TIMEOUT),
+            governor: Governor::default(),
+            clock: Clock::default(),
+        }
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    pub fn set_endpoints(&mut self, endpoints: Endpoints) {
+        self.endpoints = endpoints;
+    }
+
+    /* measure the offset to the server clock, returns the server time in seconds */
+    pub async fn sync_clock(&self) -> Result<i64> {
+        let sent = SystemTime::now();
+        let server_time = self.dispatch(&GetTimestamp {}).await?;
+        let received = SystemTime::now();
+
+        let offset = self.clock.update(server_time * 1000, sent, received);
+        if offset.abs() > CLOCK_SKEW_WARNING_MS {
+            warn!(
+                "local clock is {} ms {} the MAX server",
+                offset.abs(),
+                if offset > 0 { "behind" } else { "ahead of" }
+            );
+        }
+
+        Ok(server_time)
+    }
+
+    /* iterate over every page of a paginated endpoint */
+    pub fn pages<R: Paged>(&self, request: R) -> Pages<'_, R> {
+        Pages::new(self, request)
+    }
+
+    /* full jitter: a random delay up to the exponential backoff of the attempt */
+    pub fn backoff(attempt: u32) -> Duration {
+        let delay = RETRY_DELAY
+            .saturating_mul(1 << attempt.min(16))
+            .min(MAX_RETRY_DELAY);
+        rand::thread_rng().gen_range(Duration::ZERO..=delay)
+    }
+
+    /* rate limited send, idempotent requests are retried on transient failures */
+    pub async fn send<R: Request>(&self, request: &R) -> Result<R::Response> {
+        if R::SIGNED && self.clock.needs_sync() {
+            if let Err(err) = self.sync_clock().await {
+                warn!("failed to sync the clock with MAX: {}", err);
+            }
+        }
+
+        self.dispatch(request).await
+    }
+
+    async fn dispatch<R: Request>(&self, request: &R) -> Result<R::Response> {
+        let mut attempt = 0;
+
+        loop {
+            self.governor.acquire(R::PATH, R::RATE_LIMIT).await?;
+
+            match self.send_once(request).await {
+                Err(err) if R::IDEMPOTENT && err.is_retryable() && attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    let delay = MaxClient::backoff(attempt);
+                    warn!(
+                        "{} failed, retry {} in {:?}: {}",
+                        R::PATH,
+                        attempt,
+                        delay,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once<R: Request>(&self, request: &R) -> Result<R::Response> {
+        let path = request.path();
+        let url = format!("{}{}", self.endpoints.rest_url, path);
+
+        let builder = self.client.request(R::METHOD, url);
+        let builder = if R::SIGNED {
+            self.sign(builder, &path, request)?
+        } else {
+            builder.query(&query_pairs(request)?)
+        };
+
+        /* send the request and wait for the respond */
+        let respond = builder.send().await?;
+        let status = respond.status();
+        let body = respond.text().await?;
+
+        /* throttling and gateway failures are retried, whatever the body says */
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Err(Error::Http { status, body });
+        }
+
+        /* MAX reports rejections as {"error": {"code", "message"}} */
+        if let Ok(respond) = serde_json::from_str::<ErrorResponse>(&body) {
+            return Err(Error::Api {
+                code: respond.error.code,
+                message: respond.error.message,
+            });
+        }
+
+        if !status.is_success() {
+            return Err(Error::Http { status, body });
+        }
+
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    fn sign<R: Request>(
+        &self,
+        builder: RequestBuilder,
+        path: &str,
+        request: &R,
+    ) -> Result<RequestBuilder> {
+        /* milliseconds of the server clock, unique per request */
+        let nonce = self.clock.nonce();
+
+        /* pack the payload with Base64 format */
+        let payload = Payload {
+            nonce,
+            path,
+            params: request,
+        };
+        let payload_json = serde_json::to_string(&payload)?;
+        let payload_json_b64 = b64_encode(payload_json.as_bytes());
+
+        /* generate the signature */
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret_key.as_bytes()).unwrap();
+        signed_key.update(payload_json_b64.as_bytes());
+        let signature = hex::encode(signed_key.finalize().into_bytes());
+
+        /* setup request header */
+        let builder = builder
+            .header("X-MAX-ACCESSKEY", &self.access_key)
+            .header("X-MAX-PAYLOAD", &payload_json_b64)
+            .header("X-MAX-SIGNATURE", signature)
+            .header(header::CONTENT_TYPE, "application/json");
+
+        /* GET parameters go to the query string, the others to the body */
+        let builder = if R::METHOD == Method::GET {
+            builder.query(&query_pairs(&Query {
+                nonce,
+                params: request,
+            })?)
+        } else {
+            builder.body(payload_json)
+        };
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn timed_out_requests_are_retried() {
+        /* accept connections but never answer them */
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicU32::new(0));
+
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                sockets.push(socket);
+            }
+        });
+
+        let client = MaxClient {
+            endpoints: Endpoints {
+                rest_url: format!("http://{}", address),
+                ws_url: String::new(),
+            },
+            access_key: String::new(),
+            secret_key: String::new(),
+            client: http_client(Duration::from_millis(100), Duration::from_millis(100)),
+            governor: Governor::default(),
+            clock: Clock::default(),
+        };
+
+        match client.send(&GetTimestamp {}).await {
+            Err(Error::Transport(err)) => assert!(err.is_timeout()),
+            result => panic!("expected a timeout, got {:?}", result),
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), MAX_RETRIES + 1);
+    }
+}
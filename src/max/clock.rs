@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/* how often the offset to the MAX server clock is measured again */
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(600);
+
+/* the timestamp endpoint has a resolution of one second */
+const CLOCK_RESOLUTION_MS: i64 = 1000;
+
+/* tracks the offset between the local and the MAX clock and hands out nonces */
+#[derive(Default)]
+pub struct Clock {
+    offset_ms: AtomicI64,
+    last_nonce: AtomicU64,
+    synced: Mutex<Option<Instant>>,
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => since_epoch.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    }
+}
+
+impl Clock {
+    /* true until a sync succeeds, then again once the interval is over */
+    pub fn needs_sync(&self) -> bool {
+        match *self.synced.lock().unwrap() {
+            Some(time) => time.elapsed() >= CLOCK_SYNC_INTERVAL,
+            None => true,
+        }
+    }
+
+    /* compare the server time against the midpoint of the round trip */
+    pub fn update(&self, server_ms: i64, sent: SystemTime, received: SystemTime) -> i64 {
+        let local_ms = (unix_millis(sent) + unix_millis(received)) / 2;
+
+        let mut offset = server_ms - local_ms;
+        if offset.abs() < CLOCK_RESOLUTION_MS {
+            offset = 0;
+        }
+
+        self.offset_ms.store(offset, Ordering::SeqCst);
+        *self.synced.lock().unwrap() = Some(Instant::now());
+        offset
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::SeqCst)
+    }
+
+    /* server time in milliseconds, strictly increasing across all threads */
+    pub fn nonce(&self) -> u64 {
+        let now = (unix_millis(SystemTime::now()) + self.offset_ms()).max(0) as u64;
+
+        let previous = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+
+        now.max(previous + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::thread;
+
+    #[test]
+    fn nonce_strictly_increases() {
+        let clock = Clock::default();
+
+        let mut last = clock.nonce();
+        for _ in 0..1000 {
+            let nonce = clock.nonce();
+            assert!(nonce > last);
+            last = nonce;
+        }
+    }
+
+    #[test]
+    fn nonce_strictly_increases_when_the_offset_goes_back() {
+        let clock = Clock::default();
+        let now = SystemTime::now();
+
+        clock.update(unix_millis(now) + 60_000, now, now);
+        let before = clock.nonce();
+
+        clock.update(unix_millis(now) - 60_000, now, now);
+        assert!(clock.nonce() > before);
+    }
+
+    #[test]
+    fn nonce_is_unique_across_threads() {
+        let clock = Clock::default();
+
+        let nonces: Vec<u64> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| (0..500).map(|_| clock.nonce()).collect::<Vec<_>>()))
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(nonces.iter().collect::<HashSet<_>>().len(), nonces.len());
+    }
+
+    #[test]
+    fn needs_sync_until_an_update() {
+        let clock = Clock::default();
+        assert!(clock.needs_sync());
+
+        let now = SystemTime::now();
+        assert_eq!(clock.update(unix_millis(now) + 300, now, now), 0);
+        assert!(!clock.needs_sync());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/* requests queue behind the limit for at most this long before they are shed */
+const MAX_QUEUE_WAIT: Duration = Duration::from_secs(5);
+
+/* token bucket refilled at rate tokens per second, holding at most rate tokens */
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/* client side rate limiter with one bucket per endpoint */
+#[derive(Default)]
+pub struct Governor {
+    buckets: Mutex<HashMap<&'static str, Bucket>>,
+}
+
+impl Governor {
+    /* wait until the endpoint may be called, or shed the request if the queue is too long */
+    pub async fn acquire(&self, path: &'static str, rate: u32) -> Result<()> {
+        let rate = rate.max(1) as f64;
+
+        let wait = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let now = Instant::now();
+            let bucket = buckets.entry(path).or_insert(Bucket {
+                tokens: rate,
+                last: now,
+            });
+
+            let elapsed = now.duration_since(bucket.last).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+            bucket.last = now;
+
+            /* a negative balance is the queue of requests already waiting */
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens).max(0.0) / rate);
+            if wait > MAX_QUEUE_WAIT {
+                return Err(Error::Throttled(path.into()));
+            }
+
+            bucket.tokens -= 1.0;
+            wait
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+mod api;
+mod client;
+mod clock;
+mod governor;
+mod model;
+mod order;
+mod pager;
+
+pub use api::*;
+pub use client::{Endpoints, MaxClient, Request, MAX_RETRIES};
+pub use model::*;
+pub use order::OrderRequest;
+pub use pager::{Paged, Pages};
@@ -0,0 +1,541 @@
+use chrono::serde::{ts_milliseconds, ts_seconds};
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Side, String> {
+        match s {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            _ => Err(format!("unknown side: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/* trades report the taker side as bid or ask */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    #[serde(alias = "buy")]
+    Bid,
+    #[serde(alias = "sell")]
+    Ask,
+    #[serde(rename = "self-trade")]
+    SelfTrade,
+}
+
+impl TradeSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeSide::Bid => "bid",
+            TradeSide::Ask => "ask",
+            TradeSide::SelfTrade => "self-trade",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Market,
+    Limit,
+    StopMarket,
+    StopLimit,
+    PostOnly,
+    IocLimit,
+}
+
+impl OrderType {
+    pub const ALL: [OrderType; 6] = [
+        OrderType::Market,
+        OrderType::Limit,
+        OrderType::StopMarket,
+        OrderType::StopLimit,
+        OrderType::PostOnly,
+        OrderType::IocLimit,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderType::Market => "market",
+            OrderType::Limit => "limit",
+            OrderType::StopMarket => "stop_market",
+            OrderType::StopLimit => "stop_limit",
+            OrderType::PostOnly => "post_only",
+            OrderType::IocLimit => "ioc_limit",
+        }
+    }
+
+    /* market orders take whatever price the book offers */
+    pub fn has_price(&self) -> bool {
+        !matches!(self, OrderType::Market | OrderType::StopMarket)
+    }
+
+    /* stop orders enter the book once the stop_price is reached */
+    pub fn is_stop(&self) -> bool {
+        matches!(self, OrderType::StopMarket | OrderType::StopLimit)
+    }
+}
+
+impl FromStr for OrderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OrderType, String> {
+        match OrderType::ALL
+            .iter()
+            .find(|ord_type| ord_type.as_str() == s)
+        {
+            Some(ord_type) => Ok(*ord_type),
+            None => Err(format!("unknown order type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderState {
+    Wait,
+    Done,
+    Cancel,
+    Convert,
+    Finalizing,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+impl OrderState {
+    /* every state an order can end up in, Unknown is only a decoding fallback */
+    pub const ALL: [OrderState; 6] = [
+        OrderState::Wait,
+        OrderState::Convert,
+        OrderState::Finalizing,
+        OrderState::Done,
+        OrderState::Cancel,
+        OrderState::Failed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderState::Wait => "wait",
+            OrderState::Done => "done",
+            OrderState::Cancel => "cancel",
+            OrderState::Convert => "convert",
+            OrderState::Finalizing => "finalizing",
+            OrderState::Failed => "failed",
+            OrderState::Unknown => "unknown",
+        }
+    }
+}
+
+impl FromStr for OrderState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OrderState, String> {
+        match OrderState::ALL.iter().find(|state| state.as_str() == s) {
+            Some(state) => Ok(*state),
+            None => Err(format!("unknown order state: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Order {
+    pub id: u64,
+    pub client_oid: Option<String>,
+    pub side: Side,
+    pub ord_type: OrderType,
+    pub price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    pub avg_price: Decimal,
+    pub state: OrderState,
+    pub market: String,
+    #[serde(rename = "created_at_in_ms", with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    pub volume: Decimal,
+    pub remaining_volume: Decimal,
+    pub executed_volume: Decimal,
+    pub trades_count: u64,
+    pub group_id: Option<u64>,
+}
+
+/* a rejected order of a batch carries either a MAX error or a bare message */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OrderError {
+    Detail { code: i64, message: String },
+    Message(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderResult {
+    pub error: Option<OrderError>,
+    pub order: Option<Order>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Trade {
+    pub id: u64,
+    pub price: Decimal,
+    pub volume: Decimal,
+    pub funds: Decimal,
+    pub market: String,
+    #[serde(rename = "created_at_in_ms", with = "ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    pub side: Option<TradeSide>,
+    pub fee: Option<Decimal>,
+    pub fee_currency: Option<String>,
+    pub order_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Ticker {
+    #[serde(with = "ts_seconds")]
+    pub at: DateTime<Utc>,
+    pub buy: Decimal,
+    pub sell: Decimal,
+    pub open: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub last: Decimal,
+    pub vol: Decimal,
+}
+
+/* order book entries come as [price, volume] */
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(from = "(Decimal, Decimal)")]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub volume: Decimal,
+}
+
+impl From<(Decimal, Decimal)> for PriceLevel {
+    fn from((price, volume): (Decimal, Decimal)) -> PriceLevel {
+        PriceLevel { price, volume }
+    }
+}
+
+/* asks are sorted from the lowest price, bids from the highest */
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Depth {
+    #[serde(with = "ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub last_update_id: Option<u64>,
+    pub asks: Vec<PriceLevel>,
+    pub bids: Vec<PriceLevel>,
+}
+
+/* candles come as [timestamp, open, high, low, close, volume] */
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(from = "(i64, Decimal, Decimal, Decimal, Decimal, Decimal)")]
+pub struct Candle {
+    pub time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+impl From<(i64, Decimal, Decimal, Decimal, Decimal, Decimal)> for Candle {
+    fn from(
+        (timestamp, open, high, low, close, volume): (
+            i64,
+            Decimal,
+            Decimal,
+            Decimal,
+            Decimal,
+            Decimal,
+        ),
+    ) -> Candle {
+        Candle {
+            time: Utc.timestamp_opt(timestamp, 0).single().unwrap_or_default(),
+            open,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct Account {
+    pub currency: String,
+    pub balance: Decimal,
+    pub locked: Decimal,
+    #[serde(default)]
+    pub staked: Option<Decimal>,
+    #[serde(rename = "type")]
+    pub account_type: String,
+    pub fiat_currency: Option<String>,
+    pub fiat_balance: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct VipLevelInfo {
+    pub level: u32,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct VipLevel {
+    pub current_vip_level: VipLevelInfo,
+    pub next_vip_level: Option<VipLevelInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn order_from_max_json() {
+        let order: Order = serde_json::from_str(
+            r#"{
+                "id": 87,
+                "side": "sell",
+                "ord_type": "limit",
+                "price": "21499.0",
+                "stop_price": null,
+                "avg_price": "21499.0",
+                "state": "done",
+                "market": "ethtwd",
+                "created_at": 1521726960,
+                "created_at_in_ms": 1521726960123,
+                "updated_at": 1521726960,
+                "updated_at_in_ms": 1521726960123,
+                "volume": "0.2658",
+                "remaining_volume": "0.0",
+                "executed_volume": "0.2658",
+                "trades_count": 1,
+                "group_id": 123456,
+                "client_oid": "ccgt-1e240-65000000-3-1f"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(order.id, 87);
+        assert_eq!(order.side, Side::Sell);
+        assert_eq!(order.ord_type, OrderType::Limit);
+        assert_eq!(order.price, Some(dec!(21499.0)));
+        assert_eq!(order.stop_price, None);
+        assert_eq!(order.state, OrderState::Done);
+        assert_eq!(order.created_at.timestamp_millis(), 1521726960123);
+        assert_eq!(order.executed_volume, dec!(0.2658));
+        assert_eq!(order.group_id, Some(123456));
+        assert_eq!(
+            order.client_oid.as_deref(),
+            Some("ccgt-1e240-65000000-3-1f")
+        );
+    }
+
+    #[test]
+    fn order_with_an_unknown_state() {
+        let order: Order = serde_json::from_str(
+            r#"{
+                "id": 88,
+                "side": "buy",
+                "ord_type": "stop_market",
+                "price": null,
+                "stop_price": "20000.0",
+                "avg_price": "0.0",
+                "state": "rejected",
+                "market": "btcusdt",
+                "created_at_in_ms": 1521726960123,
+                "volume": "0.01",
+                "remaining_volume": "0.01",
+                "executed_volume": "0.0",
+                "trades_count": 0,
+                "group_id": null,
+                "client_oid": null
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(order.ord_type, OrderType::StopMarket);
+        assert_eq!(order.price, None);
+        assert_eq!(order.stop_price, Some(dec!(20000.0)));
+        assert_eq!(order.state, OrderState::Unknown);
+        assert_eq!(order.group_id, None);
+    }
+
+    #[test]
+    fn order_results_from_max_json() {
+        let results: Vec<OrderResult> = serde_json::from_str(
+            r#"[
+                {"error": {"code": 2007, "message": "order volume too small"}, "order": null},
+                {"error": "invalid price", "order": null}
+            ]"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            results[0].error,
+            Some(OrderError::Detail { code: 2007, .. })
+        ));
+        match &results[1].error {
+            Some(OrderError::Message(message)) => assert_eq!(message, "invalid price"),
+            error => panic!("expected a bare message, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn trade_from_max_json() {
+        let trade: Trade = serde_json::from_str(
+            r#"{
+                "id": 68444,
+                "price": "21499.0",
+                "volume": "0.2658",
+                "funds": "5714.9142",
+                "market": "ethtwd",
+                "market_name": "ETH/TWD",
+                "created_at": 1521726960,
+                "created_at_in_ms": 1521726960357,
+                "side": "self-trade",
+                "fee": "5.7149142",
+                "fee_currency": "twd",
+                "order_id": 18298,
+                "info": {"maker": "ask"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(trade.id, 68444);
+        assert_eq!(trade.funds, dec!(5714.9142));
+        assert_eq!(trade.created_at.timestamp_millis(), 1521726960357);
+        assert_eq!(trade.side, Some(TradeSide::SelfTrade));
+        assert_eq!(trade.fee, Some(dec!(5.7149142)));
+        assert_eq!(trade.order_id, Some(18298));
+
+        let trade: Trade = serde_json::from_str(
+            r#"{
+                "id": 68445,
+                "price": "21499.0",
+                "volume": "0.1",
+                "funds": "2149.9",
+                "market": "ethtwd",
+                "created_at_in_ms": 1521726960357,
+                "side": "bid"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(trade.side, Some(TradeSide::Bid));
+        assert_eq!(trade.fee, None);
+    }
+
+    #[test]
+    fn accounts_from_max_json() {
+        let accounts: Vec<Account> = serde_json::from_str(
+            r#"[
+                {
+                    "currency": "twd",
+                    "balance": "1000.0",
+                    "locked": "20.5",
+                    "staked": null,
+                    "type": "exchange",
+                    "fiat_currency": "twd",
+                    "fiat_balance": "1000.0"
+                },
+                {
+                    "currency": "max",
+                    "balance": "10.0",
+                    "locked": "0.0",
+                    "staked": "5.0",
+                    "type": "exchange"
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(accounts[0].currency, "twd");
+        assert_eq!(accounts[0].locked, dec!(20.5));
+        assert_eq!(accounts[0].staked, None);
+        assert_eq!(accounts[0].account_type, "exchange");
+        assert_eq!(accounts[1].staked, Some(dec!(5.0)));
+        assert_eq!(accounts[1].fiat_balance, None);
+    }
+
+    #[test]
+    fn depth_from_max_json() {
+        let depth: Depth = serde_json::from_str(
+            r#"{
+                "timestamp": 1521726960,
+                "last_update_version": 1024,
+                "last_update_id": 3321,
+                "asks": [["21500.0", "0.5"], ["21510.1", "1.25"]],
+                "bids": [["21499.0", "0.2658"]]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(depth.timestamp.timestamp(), 1521726960);
+        assert_eq!(depth.last_update_id, Some(3321));
+        assert_eq!(depth.asks.len(), 2);
+        assert_eq!(depth.asks[1].price, dec!(21510.1));
+        assert_eq!(depth.asks[1].volume, dec!(1.25));
+        assert_eq!(depth.bids[0].price, dec!(21499.0));
+        assert_eq!(depth.bids[0].volume, dec!(0.2658));
+
+        let depth: Depth =
+            serde_json::from_str(r#"{"timestamp": 1521726960, "asks": [], "bids": []}"#).unwrap();
+        assert_eq!(depth.last_update_id, None);
+    }
+
+    #[test]
+    fn candles_from_max_json() {
+        let candles: Vec<Candle> = serde_json::from_str(
+            r#"[
+                [1521726960, 21499.0, 21600.5, 21400.0, 21550.25, 12.3456],
+                [1521727020, 21550.25, 21550.25, 21550.25, 21550.25, 0]
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].time.timestamp(), 1521726960);
+        assert_eq!(candles[0].open, dec!(21499.0));
+        assert_eq!(candles[0].high, dec!(21600.5));
+        assert_eq!(candles[0].low, dec!(21400.0));
+        assert_eq!(candles[0].close, dec!(21550.25));
+        assert_eq!(candles[0].volume, dec!(12.3456));
+        assert_eq!(candles[1].time.timestamp(), 1521727020);
+        assert_eq!(candles[1].volume, Decimal::ZERO);
+    }
+}
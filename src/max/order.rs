@@ -0,0 +1,196 @@
+use super::{OrderParams, OrderType, Side};
+use rust_decimal::Decimal;
+
+/*
+ * an order before it is sent to MAX, e.g.
+ * OrderRequest::new(OrderType::StopLimit, Side::Sell, volume).price(price).stop_price(stop)
+ */
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub ord_type: OrderType,
+    pub side: Side,
+    pub volume: Decimal,
+    pub price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    pub client_oid: Option<String>,
+    pub group_id: Option<u64>,
+}
+
+impl OrderRequest {
+    pub fn new(ord_type: OrderType, side: Side, volume: Decimal) -> OrderRequest {
+        OrderRequest {
+            ord_type,
+            side,
+            volume,
+            price: None,
+            stop_price: None,
+            client_oid: None,
+            group_id: None,
+        }
+    }
+
+    pub fn price(mut self, price: Decimal) -> OrderRequest {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn stop_price(mut self, stop_price: Decimal) -> OrderRequest {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    pub fn client_oid(mut self, client_oid: String) -> OrderRequest {
+        self.client_oid = Some(client_oid);
+        self
+    }
+
+    pub fn group_id(mut self, group_id: u64) -> OrderRequest {
+        self.group_id = Some(group_id);
+        self
+    }
+
+    /* the fields each order type needs, and only those */
+    pub fn validate(&self) -> Result<(), String> {
+        let ord_type = self.ord_type.as_str();
+
+        if self.volume <= Decimal::ZERO {
+            return Err(format!(
+                "{} order volume {} is not positive",
+                ord_type, self.volume
+            ));
+        }
+
+        match (self.ord_type.has_price(), self.price) {
+            (true, None) => return Err(format!("{} order requires a price", ord_type)),
+            (true, Some(price)) if price <= Decimal::ZERO => {
+                return Err(format!(
+                    "{} order price {} is not positive",
+                    ord_type, price
+                ))
+            }
+            (false, Some(_)) => return Err(format!("{} order takes no price", ord_type)),
+            _ => {}
+        }
+
+        match (self.ord_type.is_stop(), self.stop_price) {
+            (true, None) => return Err(format!("{} order requires a stop_price", ord_type)),
+            (true, Some(stop_price)) if stop_price <= Decimal::ZERO => {
+                return Err(format!(
+                    "{} order stop_price {} is not positive",
+                    ord_type, stop_price
+                ))
+            }
+            (false, Some(_)) => return Err(format!("{} order takes no stop_price", ord_type)),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /* the validated payload of CreateOrder and CreateOrders */
+    pub fn build(self) -> Result<OrderParams, String> {
+        self.validate()?;
+
+        Ok(OrderParams {
+            side: self.side,
+            volume: self.volume,
+            price: self.price,
+            client_oid: self.client_oid,
+            stop_price: self.stop_price,
+            ord_type: self.ord_type,
+            group_id: self.group_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    /* an order of the type with exactly the fields it needs */
+    fn complete(ord_type: OrderType) -> OrderRequest {
+        let mut order = OrderRequest::new(ord_type, Side::Buy, dec!(0.01));
+        if ord_type.has_price() {
+            order = order.price(dec!(27000));
+        }
+        if ord_type.is_stop() {
+            order = order.stop_price(dec!(26000));
+        }
+        order
+    }
+
+    #[test]
+    fn complete_orders_of_every_type_are_valid() {
+        for ord_type in OrderType::ALL {
+            assert!(complete(ord_type).validate().is_ok(), "{:?}", ord_type);
+        }
+    }
+
+    #[test]
+    fn priced_orders_require_a_price() {
+        for ord_type in [
+            OrderType::Limit,
+            OrderType::StopLimit,
+            OrderType::PostOnly,
+            OrderType::IocLimit,
+        ] {
+            let mut order = complete(ord_type);
+            order.price = None;
+            assert!(order.validate().is_err(), "{:?}", ord_type);
+        }
+    }
+
+    #[test]
+    fn market_orders_take_no_price() {
+        for ord_type in [OrderType::Market, OrderType::StopMarket] {
+            assert!(complete(ord_type).price(dec!(27000)).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn stop_orders_require_a_stop_price() {
+        for ord_type in [OrderType::StopMarket, OrderType::StopLimit] {
+            let mut order = complete(ord_type);
+            order.stop_price = None;
+            assert!(order.validate().is_err(), "{:?}", ord_type);
+        }
+
+        let order = complete(OrderType::Limit).stop_price(dec!(26000));
+        assert!(order.validate().is_err());
+    }
+
+    #[test]
+    fn amounts_must_be_positive() {
+        for volume in [Decimal::ZERO, dec!(-0.01)] {
+            let mut order = complete(OrderType::Limit);
+            order.volume = volume;
+            assert!(order.validate().is_err());
+        }
+
+        for price in [Decimal::ZERO, dec!(-1)] {
+            assert!(complete(OrderType::Limit).price(price).validate().is_err());
+            assert!(complete(OrderType::StopLimit)
+                .stop_price(price)
+                .validate()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn build_keeps_the_fields() {
+        let params = complete(OrderType::StopLimit)
+            .client_oid("ccgt-1-1-0-1".into())
+            .group_id(7)
+            .build()
+            .unwrap();
+
+        assert_eq!(params.ord_type, OrderType::StopLimit);
+        assert_eq!(params.volume, dec!(0.01));
+        assert_eq!(params.price, Some(dec!(27000)));
+        assert_eq!(params.stop_price, Some(dec!(26000)));
+        assert_eq!(params.client_oid.as_deref(), Some("ccgt-1-1-0-1"));
+        assert_eq!(params.group_id, Some(7));
+        assert!(complete(OrderType::Market).price(dec!(1)).build().is_err());
+    }
+}
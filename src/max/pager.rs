@@ -0,0 +1,108 @@
+use super::{MaxClient, Request};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/* pause between page requests so long histories stay below the rate limit */
+const PAGE_INTERVAL: Duration = Duration::from_millis(250);
+
+/* an endpoint returning a list that is split into numbered pages */
+pub trait Paged: Request<Response = Vec<<Self as Paged>::Item>> {
+    type Item;
+
+    fn set_page(&mut self, page: u64);
+    fn limit(&self) -> u64;
+    fn created_at(item: &Self::Item) -> DateTime<Utc>;
+
+    /* newest items come first */
+    fn descending(&self) -> bool {
+        false
+    }
+}
+
+/* yields the items page by page, a short page marks the end of the list */
+pub struct Pages<'a, R: Paged> {
+    client: &'a MaxClient,
+    request: R,
+    page: u64,
+    items: std::vec::IntoIter<R::Item>,
+    done: bool,
+    last_fetch: Option<Instant>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl<'a, R: Paged> Pages<'a, R> {
+    pub fn new(client: &'a MaxClient, request: R) -> Pages<'a, R> {
+        Pages {
+            client,
+            request,
+            page: 0,
+            items: Vec::new().into_iter(),
+            done: false,
+            last_fetch: None,
+            since: None,
+            until: None,
+        }
+    }
+
+    /* only yield items created within [since, until), the walk ends at the far bound */
+    pub fn within(mut self, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    /* the next item, fetching the next page when the current one is used up */
+    pub async fn next(&mut self) -> Option<Result<R::Item>> {
+        loop {
+            if let Some(item) = self.items.next() {
+                let created_at = R::created_at(&item);
+                let before = matches!(self.since, Some(since) if created_at < since);
+                let after = matches!(self.until, Some(until) if created_at >= until);
+
+                /* items short of the window are skipped, the first one past it ends the walk */
+                let past_end = match self.request.descending() {
+                    true => before,
+                    false => after,
+                };
+                if past_end {
+                    self.done = true;
+                    self.items = Vec::new().into_iter();
+                    return None;
+                }
+                if before || after {
+                    continue;
+                }
+                return Some(Ok(item));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Some(time) = self.last_fetch {
+                let elapsed = time.elapsed();
+                if elapsed < PAGE_INTERVAL {
+                    tokio::time::sleep(PAGE_INTERVAL - elapsed).await;
+                }
+            }
+
+            self.page += 1;
+            self.request.set_page(self.page);
+            self.last_fetch = Some(Instant::now());
+
+            match self.client.send(&self.request).await {
+                Ok(items) => {
+                    self.done = (items.len() as u64) < self.request.limit();
+                    self.items = items.into_iter();
+                }
+                Err(err) => {
+                    /* the page is requested again on the next call, the caller decides to resume */
+                    self.page -= 1;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}